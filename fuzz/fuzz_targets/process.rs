@@ -0,0 +1,24 @@
+#![no_main]
+
+use std::io::{BufReader, Cursor};
+
+use ahoy::{Ahoy, quirks::Quirks};
+use libfuzzer_sys::fuzz_target;
+
+// The first byte picks the quirks, the rest is the program image
+fuzz_target!(|data: &[u8]| {
+    let Some((quirk_bits, program)) = data.split_first() else {
+        return;
+    };
+    let mut ahoy = Ahoy::default();
+    ahoy.quirks = Quirks::from_bits(*quirk_bits);
+    if ahoy.load(&mut BufReader::new(Cursor::new(program))).is_err() {
+        return;
+    }
+
+    for _ in 0..4096 {
+        if ahoy.process().is_err() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,233 @@
+use std::{io::Write, path::Path};
+
+use anyhow::anyhow;
+
+use crate::{
+    display::{AhoyFrame, DISPLAY_HEIGHT, DISPLAY_WIDTH, pixel},
+    export::{indexed_colors, packed_rows},
+    palette::Palette,
+};
+
+const FRAMES_PER_SECOND: u32 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+impl AnimationFormat {
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("gif") => Ok(AnimationFormat::Gif),
+            Some("png" | "apng") => Ok(AnimationFormat::Apng),
+            _ => Err(anyhow!(
+                "Cannot tell the animation format of {}, expected .gif, .png or .apng",
+                path.display()
+            )),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+        }
+    }
+}
+
+// Collects 60 Hz frames, merging repeats into longer delays. APNG needs the
+// frame count up front, so everything is encoded in `finish`
+pub struct AnimationRecorder {
+    format: AnimationFormat,
+    scale: usize,
+    palette: Palette,
+    runs: Vec<(AhoyFrame, u16)>,
+}
+
+impl AnimationRecorder {
+    pub fn new(format: AnimationFormat, scale: usize, palette: Palette) -> Self {
+        Self {
+            format,
+            scale: scale.max(1),
+            palette,
+            runs: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, frame: &AhoyFrame) {
+        match self.runs.last_mut() {
+            Some((last, ticks)) if last == frame && *ticks < u16::MAX => *ticks += 1,
+            _ => self.runs.push((*frame, 1)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    // Distinct images, not 60 Hz ticks
+    pub fn len(&self) -> usize {
+        self.runs.len()
+    }
+
+    pub fn finish<W: Write>(self, writer: &mut W) -> anyhow::Result<()> {
+        if self.runs.is_empty() {
+            return Err(anyhow!("No frames were recorded"));
+        }
+        match self.format {
+            AnimationFormat::Gif => self.write_gif(writer),
+            AnimationFormat::Apng => self.write_apng(writer),
+        }
+    }
+
+    fn write_gif<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        let (width, height) = (DISPLAY_WIDTH * self.scale, DISPLAY_HEIGHT * self.scale);
+        let too_large = || anyhow!("A scale of {} is too large for a GIF", self.scale);
+        let gif_width = u16::try_from(width).map_err(|_| too_large())?;
+        let gif_height = u16::try_from(height).map_err(|_| too_large())?;
+        let colors = indexed_colors(&self.palette);
+        let mut encoder = gif::Encoder::new(writer, gif_width, gif_height, &colors)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        // GIF delays are in centiseconds, so convert the running total rather
+        // than each frame to keep the overall speed right
+        let mut elapsed_ticks = 0_u32;
+        for (frame, ticks) in &self.runs {
+            let start = elapsed_ticks * 100 / FRAMES_PER_SECOND;
+            elapsed_ticks += *ticks as u32;
+            let end = elapsed_ticks * 100 / FRAMES_PER_SECOND;
+
+            let pixels: Vec<u8> = (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| pixel(frame, x / self.scale, y / self.scale) as u8)
+                .collect();
+            let mut image = gif::Frame::from_indexed_pixels(gif_width, gif_height, pixels, None);
+            image.delay = (end - start).min(u16::MAX as u32) as u16;
+            encoder.write_frame(&image)?;
+        }
+        Ok(())
+    }
+
+    fn write_apng<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        let mut encoder = png::Encoder::new(
+            writer,
+            (DISPLAY_WIDTH * self.scale) as u32,
+            (DISPLAY_HEIGHT * self.scale) as u32,
+        );
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::One);
+        encoder.set_palette(indexed_colors(&self.palette));
+        encoder.set_animated(self.runs.len() as u32, 0)?;
+
+        let mut writer = encoder.write_header()?;
+        for (frame, ticks) in &self.runs {
+            writer.set_frame_delay(*ticks, FRAMES_PER_SECOND as u16)?;
+            let data: Vec<u8> = packed_rows(frame, self.scale)
+                .into_iter()
+                .flatten()
+                .collect();
+            writer.write_image_data(&data)?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, path::Path};
+
+    use crate::{
+        animation::{AnimationFormat, AnimationRecorder},
+        display::DISPLAY_HEIGHT,
+        palette::Palette,
+    };
+
+    fn blinking_recorder(format: AnimationFormat) -> AnimationRecorder {
+        let mut lit = [0; DISPLAY_HEIGHT];
+        lit[0] = 1 << 63;
+        let mut recorder = AnimationRecorder::new(format, 2, Palette::default());
+        for frame in [lit, lit, lit, [0; DISPLAY_HEIGHT], lit] {
+            recorder.record(&frame);
+        }
+        recorder
+    }
+
+    #[test]
+    fn picks_the_format_from_the_extension() {
+        assert_eq!(
+            AnimationFormat::from_path(Path::new("run.gif")).unwrap(),
+            AnimationFormat::Gif
+        );
+        assert_eq!(
+            AnimationFormat::from_path(Path::new("run.apng")).unwrap(),
+            AnimationFormat::Apng
+        );
+        assert!(AnimationFormat::from_path(Path::new("run.mp4")).is_err());
+    }
+
+    #[test]
+    fn repeated_frames_become_one_longer_image() {
+        assert_eq!(blinking_recorder(AnimationFormat::Gif).len(), 3);
+    }
+
+    #[test]
+    fn finish_refuses_an_empty_recording() {
+        let recorder = AnimationRecorder::new(AnimationFormat::Gif, 1, Palette::default());
+
+        recorder
+            .finish(&mut Vec::new())
+            .expect_err("Expected an empty recording to raise error");
+    }
+
+    #[test]
+    fn gif_refuses_scales_past_its_dimension_limit() {
+        let mut recorder = AnimationRecorder::new(AnimationFormat::Gif, 1024, Palette::default());
+        recorder.record(&[0; DISPLAY_HEIGHT]);
+
+        recorder
+            .finish(&mut Vec::new())
+            .expect_err("Expected an oversized GIF to raise error");
+    }
+
+    #[test]
+    fn gif_keeps_60hz_timing_in_centiseconds() {
+        let mut written = Vec::new();
+        blinking_recorder(AnimationFormat::Gif)
+            .finish(&mut written)
+            .unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(Cursor::new(written)).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (128, 64));
+
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+            if delays.len() == 1 {
+                assert_eq!(&frame.buffer[..3], &[1, 1, 0]);
+            }
+        }
+        assert_eq!(delays, vec![5, 1, 2]);
+    }
+
+    #[test]
+    fn apng_stores_one_frame_per_distinct_image() {
+        let mut written = Vec::new();
+        blinking_recorder(AnimationFormat::Apng)
+            .finish(&mut written)
+            .unwrap();
+
+        let mut reader = png::Decoder::new(Cursor::new(written)).read_info().unwrap();
+        let animation = reader.info().animation_control.unwrap();
+        assert_eq!(animation.num_frames, 3);
+
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        reader.next_frame(&mut pixels).unwrap();
+        let control = reader.info().frame_control.unwrap();
+        assert_eq!((control.delay_num, control.delay_den), (3, 60));
+        assert_eq!(pixels[0], 0b1100_0000);
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    fmt::Write as _,
+    io::Write,
+    time::{Duration, Instant},
+};
+
+// Writes an asciinema v2 cast: a JSON header line followed by one
+// `[seconds, "o", text]` line per chunk of terminal output
+pub struct CastWriter<W: Write> {
+    writer: W,
+    // Bytes of a UTF-8 character split across two writes
+    pending: Vec<u8>,
+}
+
+impl<W: Write> CastWriter<W> {
+    pub fn new(
+        mut writer: W,
+        width: u16,
+        height: u16,
+        timestamp: u64,
+        title: &str,
+    ) -> anyhow::Result<Self> {
+        writeln!(
+            writer,
+            "{{\"version\": 2, \"width\": {width}, \"height\": {height}, \"timestamp\": {timestamp}, \"title\": {}}}",
+            json_string(title)
+        )?;
+        Ok(Self {
+            writer,
+            pending: Vec::new(),
+        })
+    }
+
+    pub fn output(&mut self, time: Duration, data: &[u8]) -> anyhow::Result<()> {
+        self.pending.extend_from_slice(data);
+        let complete = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            // Only hold back a character that may still be completed
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            Err(_) => self.pending.len(),
+        };
+        if complete == 0 {
+            return Ok(());
+        }
+
+        let text = String::from_utf8_lossy(&self.pending[..complete]).into_owned();
+        self.pending.drain(..complete);
+        writeln!(
+            self.writer,
+            "[{:.6}, \"o\", {}]",
+            time.as_secs_f64(),
+            json_string(&text)
+        )?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 || c == '\u{7F}' => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Passes output through to the terminal while copying it into a cast. Terminal
+// backends write in tiny pieces, so each flush becomes one cast event
+pub struct CastTee<T: Write, W: Write> {
+    terminal: T,
+    cast: CastWriter<W>,
+    started: Instant,
+    unflushed: Vec<u8>,
+}
+
+impl<T: Write, W: Write> CastTee<T, W> {
+    pub fn new(terminal: T, cast: CastWriter<W>) -> Self {
+        Self {
+            terminal,
+            cast,
+            started: Instant::now(),
+            unflushed: Vec::new(),
+        }
+    }
+}
+
+impl<T: Write, W: Write> Write for CastTee<T, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.terminal.write(buf)?;
+        self.unflushed.extend_from_slice(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.terminal.flush()?;
+        if !self.unflushed.is_empty() {
+            self.cast
+                .output(self.started.elapsed(), &self.unflushed)
+                .map_err(std::io::Error::other)?;
+            self.unflushed.clear();
+        }
+        self.cast.flush().map_err(std::io::Error::other)
+    }
+}
+
+impl<T: Write, W: Write> Drop for CastTee<T, W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, time::Duration};
+
+    use serde_json::{Value, json};
+
+    use crate::cast::{CastTee, CastWriter};
+
+    fn lines(written: &[u8]) -> Vec<Value> {
+        String::from_utf8(written.to_vec())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn header_describes_the_terminal() {
+        let mut written = Vec::new();
+        CastWriter::new(&mut written, 80, 24, 1_700_000_000, "ibm \"logo\"").unwrap();
+
+        assert_eq!(
+            lines(&written),
+            vec![json!({
+                "version": 2,
+                "width": 80,
+                "height": 24,
+                "timestamp": 1_700_000_000,
+                "title": "ibm \"logo\""
+            })]
+        );
+    }
+
+    #[test]
+    fn output_events_carry_time_and_escaped_text() {
+        let mut written = Vec::new();
+        let mut cast = CastWriter::new(&mut written, 80, 24, 0, "").unwrap();
+        cast.output(Duration::from_millis(1500), b"\x1b[H\xe2\x96\x80\n")
+            .unwrap();
+        drop(cast);
+
+        assert_eq!(lines(&written)[1], json!([1.5, "o", "\u{1b}[H▀\n"]));
+    }
+
+    #[test]
+    fn split_characters_wait_for_their_remaining_bytes() {
+        let mut written = Vec::new();
+        let mut cast = CastWriter::new(&mut written, 80, 24, 0, "").unwrap();
+        cast.output(Duration::ZERO, b"a\xe2\x96").unwrap();
+        cast.output(Duration::from_secs(1), b"\x80b").unwrap();
+        drop(cast);
+
+        let events = lines(&written);
+        assert_eq!(events[1], json!([0.0, "o", "a"]));
+        assert_eq!(events[2], json!([1.0, "o", "▀b"]));
+    }
+
+    #[test]
+    fn tee_writes_to_the_terminal_and_the_cast() {
+        let (mut terminal, mut written) = (Vec::new(), Vec::new());
+        let mut tee = CastTee::new(
+            &mut terminal,
+            CastWriter::new(&mut written, 80, 24, 0, "").unwrap(),
+        );
+        tee.write_all(b"hel").unwrap();
+        tee.write_all(b"lo").unwrap();
+        tee.flush().unwrap();
+        tee.write_all(b"!").unwrap();
+        drop(tee);
+
+        assert_eq!(terminal, b"hello!");
+        let events = lines(&written);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[1][2], json!("hello"));
+        assert_eq!(events[2][2], json!("!"));
+    }
+}
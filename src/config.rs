@@ -0,0 +1,333 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::anyhow;
+use crossterm::event::{KeyCode, KeyEvent};
+use serde::{Deserialize, Deserializer, de::Error as _};
+
+use crate::{
+    controls::Control,
+    display::Renderer,
+    filter::FilterMode,
+    palette::Palette,
+    quirks::{Platform, Quirks},
+};
+
+// Host key for each CHIP-8 key, laid out like the COSMAC VIP keypad on 1234/QWER/ASDF/ZXCV
+pub const DEFAULT_KEYMAP: [char; 16] = [
+    'x', '1', '2', '3', 'q', 'w', 'e', 'a', 's', 'd', 'z', 'c', '4', 'r', 'f', 'v',
+];
+
+// Parses settings stored as strings with the type's own `FromStr`
+fn parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = anyhow::Error>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| value.parse().map_err(D::Error::custom))
+        .transpose()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct QuirkSettings {
+    pub vf_reset: Option<bool>,
+    pub memory_increment: Option<bool>,
+    pub display_wait: Option<bool>,
+    pub clipping: Option<bool>,
+    pub shifting: Option<bool>,
+    pub jumping: Option<bool>,
+}
+
+impl From<Quirks> for QuirkSettings {
+    fn from(quirks: Quirks) -> Self {
+        Self {
+            vf_reset: Some(quirks.vf_reset),
+            memory_increment: Some(quirks.memory_increment),
+            display_wait: Some(quirks.display_wait),
+            clipping: Some(quirks.clipping),
+            shifting: Some(quirks.shifting),
+            jumping: Some(quirks.jumping),
+        }
+    }
+}
+
+impl QuirkSettings {
+    fn or(self, fallback: Self) -> Self {
+        Self {
+            vf_reset: self.vf_reset.or(fallback.vf_reset),
+            memory_increment: self.memory_increment.or(fallback.memory_increment),
+            display_wait: self.display_wait.or(fallback.display_wait),
+            clipping: self.clipping.or(fallback.clipping),
+            shifting: self.shifting.or(fallback.shifting),
+            jumping: self.jumping.or(fallback.jumping),
+        }
+    }
+
+    fn apply(self, quirks: Quirks) -> Quirks {
+        Quirks {
+            vf_reset: self.vf_reset.unwrap_or(quirks.vf_reset),
+            memory_increment: self.memory_increment.unwrap_or(quirks.memory_increment),
+            display_wait: self.display_wait.unwrap_or(quirks.display_wait),
+            clipping: self.clipping.unwrap_or(quirks.clipping),
+            shifting: self.shifting.unwrap_or(quirks.shifting),
+            jumping: self.jumping.unwrap_or(quirks.jumping),
+        }
+    }
+}
+
+// Everything a config section or the command line can set; unset fields fall
+// through to the next layer
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RomSettings {
+    #[serde(deserialize_with = "parsed")]
+    pub platform: Option<Platform>,
+    pub quirks: QuirkSettings,
+    pub instructions_per_frame: Option<usize>,
+    #[serde(deserialize_with = "parsed")]
+    pub palette: Option<Palette>,
+    #[serde(deserialize_with = "parsed")]
+    pub filter: Option<FilterMode>,
+    #[serde(deserialize_with = "parsed")]
+    pub renderer: Option<Renderer>,
+    // CHIP-8 key in hex to the host key that presses it
+    pub keys: BTreeMap<String, char>,
+}
+
+impl RomSettings {
+    pub fn or(self, fallback: Self) -> Self {
+        let mut keys = fallback.keys;
+        keys.extend(self.keys);
+        Self {
+            platform: self.platform.or(fallback.platform),
+            // Picking a platform discards the quirks of the layers below
+            quirks: match self.platform {
+                Some(_) => self.quirks,
+                None => self.quirks.or(fallback.quirks),
+            },
+            instructions_per_frame: self
+                .instructions_per_frame
+                .or(fallback.instructions_per_frame),
+            palette: self.palette.or(fallback.palette),
+            filter: self.filter.or(fallback.filter),
+            renderer: self.renderer.or(fallback.renderer),
+            keys,
+        }
+    }
+
+    // Individual quirks win over the platform preset
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+            .apply(self.platform.map(Quirks::from).unwrap_or_default())
+    }
+
+    pub fn keymap(&self) -> anyhow::Result<[char; 16]> {
+        let mut keymap = DEFAULT_KEYMAP;
+        for (key, host) in &self.keys {
+            let key = u8::from_str_radix(key, 16)
+                .ok()
+                .filter(|key| *key < 16)
+                .ok_or_else(|| anyhow!("Expected a CHIP-8 key from 0 to F, got {:?}", key))?;
+            let host = host.to_ascii_lowercase();
+            if Control::for_key(KeyEvent::from(KeyCode::Char(host))).is_some() {
+                return Err(anyhow!("{:?} is already an emulator hotkey", host));
+            }
+            keymap[key as usize] = host;
+        }
+        // Checked once everything is bound, so two keys can swap places
+        for (key, host) in keymap.iter().enumerate() {
+            if let Some(other) = keymap[key + 1..].iter().position(|other| other == host) {
+                return Err(anyhow!(
+                    "{:?} is bound to both CHIP-8 keys {:X} and {:X}",
+                    host,
+                    key,
+                    key + 1 + other
+                ));
+            }
+        }
+        Ok(keymap)
+    }
+}
+
+// `[default]` applies to every ROM, then `[rom."<name>"]` sections keyed by
+// file name or SHA-1, with the hash taking precedence
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    default: RomSettings,
+    rom: HashMap<String, RomSettings>,
+}
+
+impl FromStr for Config {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(toml::from_str(value)?)
+    }
+}
+
+impl Config {
+    // `$XDG_CONFIG_HOME/ahoy/config.toml`, usually `~/.config/ahoy/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(config_home.join("ahoy").join("config.toml"))
+    }
+
+    // A missing file is an empty config, but a broken one is an error
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => text
+                .parse()
+                .map_err(|error| anyhow!("Could not read {}: {}", path.display(), error)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn for_rom(&self, file_name: &str, sha1: &str) -> RomSettings {
+        [sha1, file_name]
+            .iter()
+            .filter_map(|key| self.rom.get(*key))
+            .fold(RomSettings::default(), |settings, section| {
+                settings.or(section.clone())
+            })
+            .or(self.default.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::{Config, DEFAULT_KEYMAP, RomSettings},
+        database::sha1_hex,
+        display::Renderer,
+        filter::FilterMode,
+        palette::Palette,
+        quirks::{Platform, Quirks},
+    };
+
+    const CONFIG: &str = r#"
+        [default]
+        palette = "amber"
+        instructions-per-frame = 15
+
+        [rom."pong.ch8"]
+        platform = "schip"
+        filter = "blend:2"
+        quirks = { clipping = false }
+        keys = { "1" = "u", "C" = "J" }
+
+        [rom."da39a3ee5e6b4b0d3255bfef95601890afd80709"]
+        instructions-per-frame = 30
+        renderer = "braille"
+    "#;
+
+    #[test]
+    fn sections_layer_hash_over_name_over_default() {
+        let config: Config = CONFIG.parse().unwrap();
+        let settings = config.for_rom("pong.ch8", &sha1_hex(b""));
+
+        assert_eq!(settings.instructions_per_frame, Some(30));
+        assert_eq!(settings.renderer, Some(Renderer::Braille));
+        assert_eq!(settings.platform, Some(Platform::SuperChip));
+        assert_eq!(settings.filter, Some(FilterMode::Blend(2)));
+        assert_eq!(settings.palette, Palette::named("amber"));
+
+        let other = config.for_rom("tetris.ch8", "0");
+        assert_eq!(other.instructions_per_frame, Some(15));
+        assert_eq!(other.platform, None);
+    }
+
+    #[test]
+    fn quirks_start_from_the_platform_preset() {
+        let config: Config = CONFIG.parse().unwrap();
+        let quirks = config.for_rom("pong.ch8", "0").quirks();
+
+        assert_eq!(
+            quirks,
+            Quirks {
+                clipping: false,
+                ..Quirks::from(Platform::SuperChip)
+            }
+        );
+        assert_eq!(RomSettings::default().quirks(), Quirks::default());
+    }
+
+    #[test]
+    fn command_line_settings_override_the_file() {
+        let config: Config = CONFIG.parse().unwrap();
+        let cli = RomSettings {
+            palette: Palette::named("green"),
+            ..Default::default()
+        };
+        let settings = cli.or(config.for_rom("pong.ch8", "0"));
+
+        assert_eq!(settings.palette, Palette::named("green"));
+        assert_eq!(settings.instructions_per_frame, Some(15));
+    }
+
+    #[test]
+    fn a_platform_replaces_quirks_from_lower_layers() {
+        let cli = RomSettings {
+            platform: Some(Platform::XoChip),
+            ..Default::default()
+        };
+        let known = RomSettings {
+            quirks: Quirks::from(Platform::SuperChip).into(),
+            ..Default::default()
+        };
+
+        assert_eq!(cli.or(known.clone()).quirks(), Platform::XoChip.into());
+        assert_eq!(
+            RomSettings::default().or(known).quirks(),
+            Platform::SuperChip.into()
+        );
+    }
+
+    #[test]
+    fn key_bindings_replace_single_keys() {
+        let config: Config = CONFIG.parse().unwrap();
+        let keymap = config.for_rom("pong.ch8", "0").keymap().unwrap();
+
+        assert_eq!(keymap[0x1], 'u');
+        assert_eq!(keymap[0xC], 'j');
+        assert_eq!(keymap[0x0], DEFAULT_KEYMAP[0x0]);
+
+        let clashing: Config = "[default]\nkeys = { \"1\" = \"p\" }".parse().unwrap();
+        assert!(clashing.for_rom("", "").keymap().is_err());
+        let out_of_range: Config = "[default]\nkeys = { \"10\" = \"u\" }".parse().unwrap();
+        assert!(out_of_range.for_rom("", "").keymap().is_err());
+    }
+
+    #[test]
+    fn key_bindings_reject_host_keys_used_twice() {
+        let duplicate: Config = "[default]\nkeys = { \"1\" = \"q\" }".parse().unwrap();
+        let error = duplicate.for_rom("", "").keymap().unwrap_err();
+        assert!(error.to_string().contains("keys 1 and 4"));
+
+        let swapped: Config = "[default]\nkeys = { \"1\" = \"q\", \"4\" = \"1\" }"
+            .parse()
+            .unwrap();
+        let keymap = swapped.for_rom("", "").keymap().unwrap();
+        assert_eq!((keymap[0x1], keymap[0x4]), ('q', '1'));
+    }
+
+    #[test]
+    fn rejects_unknown_settings_and_bad_values() {
+        assert!("[default]\nspeed = 2".parse::<Config>().is_err());
+        assert!(
+            "[default]\nplatform = \"megachip\""
+                .parse::<Config>()
+                .is_err()
+        );
+        assert!("[default]\npalette = \"sepia\"".parse::<Config>().is_err());
+    }
+}
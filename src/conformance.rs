@@ -0,0 +1,224 @@
+use std::{
+    io::{BufReader, Cursor},
+    ops::Range,
+};
+
+use crate::{
+    Ahoy,
+    display::AhoyFrame,
+    quirks::{Platform, Quirks},
+};
+
+// 5-quirks skips its platform menu when this byte is set
+const PLATFORM_SELECT_ADDR: usize = 0x1FF;
+const INSTRUCTIONS_PER_FRAME: usize = 11;
+
+pub struct TestRom {
+    pub file: &'static str,
+    pub frames: u64,
+    pub checks: &'static [&'static str],
+    // Whether the ROM reads the platform from `PLATFORM_SELECT_ADDR`
+    pub selects_platform: bool,
+}
+
+// 6-keypad waits for input and 7-beep only passes audibly, so neither is run
+pub const TEST_SUITE: [TestRom; 3] = [
+    TestRom {
+        file: "3-corax+.ch8",
+        frames: 300,
+        checks: &[],
+        selects_platform: false,
+    },
+    TestRom {
+        file: "4-flags.ch8",
+        frames: 300,
+        checks: &[],
+        selects_platform: false,
+    },
+    // One result line per quirk, top to bottom
+    TestRom {
+        file: "5-quirks.ch8",
+        frames: 600,
+        checks: &[
+            "vF reset",
+            "memory",
+            "display wait",
+            "clipping",
+            "shifting",
+            "jumping",
+        ],
+        selects_platform: true,
+    },
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CheckResult {
+    pub name: String,
+    pub passed: bool,
+}
+
+pub fn run_test_rom(
+    program: &[u8],
+    rom: &TestRom,
+    platform: Platform,
+) -> anyhow::Result<AhoyFrame> {
+    let mut ahoy = load_test_rom(program, rom, platform)?;
+    for _ in 0..rom.frames {
+        ahoy.run_frame(INSTRUCTIONS_PER_FRAME)?;
+    }
+    Ok(ahoy.current_frame)
+}
+
+fn load_test_rom(program: &[u8], rom: &TestRom, platform: Platform) -> anyhow::Result<Ahoy> {
+    let mut ahoy = Ahoy {
+        quirks: Quirks::from(platform),
+        ..Default::default()
+    };
+    ahoy.load(&mut BufReader::new(Cursor::new(program)))?;
+    if rom.selects_platform {
+        ahoy.memory[PLATFORM_SELECT_ADDR] = match platform {
+            Platform::Chip8 => 1,
+            Platform::SuperChip => 2,
+            Platform::XoChip => 3,
+        };
+    }
+    Ok(ahoy)
+}
+
+pub fn compare_with_golden(
+    frame: &AhoyFrame,
+    golden: &AhoyFrame,
+    rom: &TestRom,
+) -> Vec<CheckResult> {
+    if rom.checks.is_empty() {
+        return vec![CheckResult {
+            name: "frame".to_owned(),
+            passed: frame == golden,
+        }];
+    }
+
+    let lines = text_lines(golden);
+    rom.checks
+        .iter()
+        .enumerate()
+        .map(|(line, name)| CheckResult {
+            name: (*name).to_owned(),
+            passed: lines
+                .get(line)
+                .is_some_and(|rows| frame[rows.clone()] == golden[rows.clone()]),
+        })
+        .collect()
+}
+
+// Runs of lit rows separated by blank ones, i.e. lines of text on screen
+fn text_lines(frame: &AhoyFrame) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = None;
+    let rows = frame.iter().chain(std::iter::once(&0)).enumerate();
+    for (row, pixels) in rows {
+        match (start, *pixels != 0) {
+            (None, true) => start = Some(row),
+            (Some(line_start), false) => {
+                lines.push(line_start..row);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        conformance::{
+            CheckResult, PLATFORM_SELECT_ADDR, TEST_SUITE, TestRom, compare_with_golden,
+            load_test_rom, run_test_rom, text_lines,
+        },
+        display::DISPLAY_HEIGHT,
+        quirks::Platform,
+    };
+
+    #[test]
+    fn text_lines_splits_on_blank_rows() {
+        let mut frame = [0; DISPLAY_HEIGHT];
+        frame[1..4].fill(1);
+        frame[6] = 1;
+        frame[30..32].fill(1);
+
+        assert_eq!(text_lines(&frame), vec![1..4, 6..7, 30..32]);
+    }
+
+    #[test]
+    fn compare_reports_each_check_separately() {
+        let quirks = &TEST_SUITE[2];
+        let mut golden = [0; DISPLAY_HEIGHT];
+        for line in 0..quirks.checks.len() {
+            golden[line * 5..line * 5 + 3].fill(0xFF);
+        }
+        let mut frame = golden;
+        frame[11] = 0xF0;
+
+        let results = compare_with_golden(&frame, &golden, quirks);
+
+        assert_eq!(results.len(), 6);
+        assert_eq!(
+            results[2],
+            CheckResult {
+                name: "display wait".to_owned(),
+                passed: false
+            }
+        );
+        assert_eq!(results.iter().filter(|result| result.passed).count(), 5);
+    }
+
+    #[test]
+    fn compare_without_checks_matches_the_whole_frame() {
+        let golden = [0; DISPLAY_HEIGHT];
+        let mut frame = golden;
+        frame[31] = 1;
+
+        let results = compare_with_golden(&frame, &golden, &TEST_SUITE[0]);
+
+        assert_eq!(results.len(), 1);
+        assert!(!results[0].passed);
+    }
+
+    #[test]
+    fn only_the_quirks_rom_gets_its_platform_selected() {
+        // JP 0x200
+        let program = [0x12, 0x00];
+        let mut rom = TestRom {
+            file: "idle.ch8",
+            frames: 1,
+            checks: &[],
+            selects_platform: false,
+        };
+
+        let ahoy = load_test_rom(&program, &rom, Platform::SuperChip).unwrap();
+        assert_eq!(ahoy.memory[PLATFORM_SELECT_ADDR], 0);
+
+        rom.selects_platform = true;
+        let ahoy = load_test_rom(&program, &rom, Platform::SuperChip).unwrap();
+        assert_eq!(ahoy.memory[PLATFORM_SELECT_ADDR], 2);
+    }
+
+    #[test]
+    fn run_test_rom_applies_the_platform_quirks() {
+        // LD VF,7 / OR V0,V1 / LD F,VF / DRW V0,V0,5 / JP 0x208
+        let program = [0x6F, 0x07, 0x80, 0x11, 0xFF, 0x29, 0xD0, 0x05, 0x12, 0x08];
+        let rom = TestRom {
+            file: "vf-reset.ch8",
+            frames: 2,
+            checks: &[],
+            selects_platform: false,
+        };
+
+        let chip8 = run_test_rom(&program, &rom, Platform::Chip8).unwrap();
+        let xochip = run_test_rom(&program, &rom, Platform::XoChip).unwrap();
+
+        // VF is reset to 0 only on CHIP-8, so each draws a different digit
+        assert_ne!(chip8, xochip);
+        assert_ne!(chip8, [0; DISPLAY_HEIGHT]);
+    }
+}
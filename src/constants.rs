@@ -1,6 +1,9 @@
 pub(crate) const PROGRAM_MEMORY_START: usize = 0x200;
 pub(crate) const MAX_MEMORY: usize = 0x1000;
 pub(crate) const AVAILABLE_PROGRAM_MEMORY: usize = MAX_MEMORY - PROGRAM_MEMORY_START;
+pub(crate) const MAX_STACK_DEPTH: usize = 256;
+pub(crate) const FONT_START: usize = 0x050;
+pub(crate) const FONT_CHARACTER_HEIGHT: usize = 5;
 pub(crate) const FONT: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70, 0xF0, 0x10, 0xF0, 0x80, 0xF0, 0xF0,
     0x10, 0xF0, 0x10, 0xF0, 0x90, 0x90, 0xF0, 0x10, 0x10, 0xF0, 0x80, 0xF0, 0x10, 0xF0, 0xF0, 0x80,
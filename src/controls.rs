@@ -0,0 +1,206 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+// Speed steps are powers of two, from an eighth up to eight times the normal rate
+const SLOWEST: i8 = -3;
+const FASTEST: i8 = 3;
+// Whole frames emulated per 60 Hz tick while turbo is on, timers included
+pub const TURBO_FRAMES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Quit,
+    Pause,
+    Advance,
+    Faster,
+    Slower,
+    Turbo,
+    Reset,
+    Overlay,
+    Keypad,
+}
+
+impl Control {
+    // None of these overlap the 1234/QWER/ASDF/ZXCV keypad
+    pub fn for_key(key: KeyEvent) -> Option<Self> {
+        match key.code {
+            KeyCode::Esc => Some(Control::Quit),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(Control::Quit)
+            }
+            KeyCode::Char('p' | 'P' | ' ') => Some(Control::Pause),
+            KeyCode::Char('n' | 'N') => Some(Control::Advance),
+            KeyCode::Char('+' | '=') => Some(Control::Faster),
+            KeyCode::Char('-' | '_') => Some(Control::Slower),
+            KeyCode::Char('t' | 'T') => Some(Control::Turbo),
+            KeyCode::F(9) => Some(Control::Reset),
+            KeyCode::Char('h' | 'H' | '?') => Some(Control::Overlay),
+            KeyCode::Char('k' | 'K') => Some(Control::Keypad),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Controls {
+    pub paused: bool,
+    pub turbo: bool,
+    pub overlay: bool,
+    pub keypad: bool,
+    speed: i8,
+    advance: bool,
+}
+
+impl Controls {
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.advance = false;
+    }
+
+    // Steps a single frame, pausing first if the emulator was running
+    pub fn advance(&mut self) {
+        if self.paused {
+            self.advance = true;
+        } else {
+            self.paused = true;
+        }
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(FASTEST);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = (self.speed - 1).max(SLOWEST);
+    }
+
+    pub fn is_normal_speed(&self) -> bool {
+        self.speed == 0
+    }
+
+    pub fn instructions_per_frame(&self, normal: usize) -> usize {
+        match self.speed {
+            speed @ 0.. => normal << speed,
+            speed => (normal >> -speed).max(1),
+        }
+    }
+
+    // How many frames to emulate this tick, consuming a pending advance
+    pub fn frames_this_tick(&mut self) -> usize {
+        match (self.paused, self.turbo) {
+            (true, _) => std::mem::take(&mut self.advance) as usize,
+            (false, true) => TURBO_FRAMES,
+            (false, false) => 1,
+        }
+    }
+
+    pub fn speed_label(&self) -> String {
+        match self.speed {
+            speed @ 0.. => format!("x{}", 1 << speed),
+            speed => format!("x1/{}", 1 << -speed),
+        }
+    }
+
+    // Appends whatever differs from plain running to a status line
+    pub fn status(&self, base: &str) -> String {
+        let mut status = base.to_owned();
+        if self.paused {
+            status.push_str(", paused");
+        }
+        if !self.is_normal_speed() {
+            status.push_str(", ");
+            status.push_str(&self.speed_label());
+        }
+        if self.turbo {
+            status.push_str(", turbo");
+        }
+        status
+    }
+
+    // Shown while paused or when asked for, otherwise out of the way
+    pub fn overlay_lines(&self) -> Vec<String> {
+        if !self.overlay && !self.paused {
+            return Vec::new();
+        }
+        let state = if self.paused { "Paused" } else { "Running" };
+        let turbo = if self.turbo { "on" } else { "off" };
+        vec![
+            format!("{state}  speed {}  turbo {turbo}", self.speed_label()),
+            "p pause    n step".to_owned(),
+            "+/- speed  t turbo".to_owned(),
+            "F9 reset   h help".to_owned(),
+            "k keypad   Esc quit".to_owned(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::controls::{Control, Controls, TURBO_FRAMES};
+
+    #[test]
+    fn control_keys_leave_the_keypad_alone() {
+        for c in "1234qwerasdfzxcvQWERASDFZXCV".chars() {
+            assert_eq!(Control::for_key(KeyEvent::from(KeyCode::Char(c))), None);
+        }
+        assert_eq!(
+            Control::for_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Control::Quit)
+        );
+        assert_eq!(
+            Control::for_key(KeyEvent::from(KeyCode::Esc)),
+            Some(Control::Quit)
+        );
+    }
+
+    #[test]
+    fn advancing_runs_exactly_one_frame_while_paused() {
+        let mut controls = Controls::default();
+        assert_eq!(controls.frames_this_tick(), 1);
+
+        controls.advance();
+        assert!(controls.paused);
+        assert_eq!(controls.frames_this_tick(), 0);
+
+        controls.advance();
+        assert_eq!(controls.frames_this_tick(), 1);
+        assert_eq!(controls.frames_this_tick(), 0);
+
+        controls.turbo = true;
+        controls.toggle_pause();
+        assert_eq!(controls.frames_this_tick(), TURBO_FRAMES);
+    }
+
+    #[test]
+    fn speed_scales_instructions_per_frame_within_limits() {
+        let mut controls = Controls::default();
+        for _ in 0..5 {
+            controls.faster();
+        }
+        assert_eq!(controls.instructions_per_frame(11), 88);
+        assert_eq!(controls.speed_label(), "x8");
+
+        for _ in 0..10 {
+            controls.slower();
+        }
+        assert_eq!(controls.instructions_per_frame(11), 1);
+        assert_eq!(controls.speed_label(), "x1/8");
+    }
+
+    #[test]
+    fn status_only_mentions_what_changed() {
+        let mut controls = Controls::default();
+        assert_eq!(controls.status("running"), "running");
+        assert!(controls.overlay_lines().is_empty());
+
+        controls.toggle_pause();
+        controls.slower();
+        controls.turbo = true;
+        assert_eq!(
+            controls.status("recording"),
+            "recording, paused, x1/2, turbo"
+        );
+        assert!(controls.overlay_lines()[0].starts_with("Paused"));
+    }
+}
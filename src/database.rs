@@ -0,0 +1,213 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::LazyLock,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    config::{QuirkSettings, RomSettings},
+    quirks::{Platform, Quirks},
+};
+
+// A subset of the community chip-8-database in its upstream `programs.json`
+// layout, so the full file can be dropped in as a replacement
+const PROGRAMS: &str = include_str!("../assets/database/programs.json");
+
+// The file is bundled at build time, so a parse error is a packaging bug
+static DATABASE: LazyLock<Vec<Program>> = LazyLock::new(|| {
+    serde_json::from_str(PROGRAMS)
+        .unwrap_or_else(|error| panic!("Bundled programs.json is invalid: {error}"))
+});
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Program {
+    title: String,
+    description: Option<String>,
+    release: Option<String>,
+    authors: Vec<String>,
+    roms: HashMap<String, RomEntry>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct RomEntry {
+    file: Option<String>,
+    platforms: Vec<String>,
+    quirky_platforms: HashMap<String, QuirkyPlatform>,
+    tickrate: Option<usize>,
+    colors: Option<Colors>,
+    keys: BTreeMap<String, u8>,
+}
+
+// Where a ROM departs from its platform's usual quirks
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct QuirkyPlatform {
+    shift: Option<bool>,
+    memory_increment_by_x: Option<bool>,
+    memory_leave_i_unchanged: Option<bool>,
+    wrap: Option<bool>,
+    jump: Option<bool>,
+    vblank: Option<bool>,
+    logic: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Colors {
+    pixels: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RomInfo {
+    pub sha1: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub release: Option<String>,
+    pub authors: Vec<String>,
+    pub file: Option<String>,
+    pub platforms: Vec<String>,
+    pub tickrate: Option<usize>,
+    pub colors: Vec<String>,
+    // What the game uses each CHIP-8 key for, e.g. "up" -> 5
+    pub keys: BTreeMap<String, u8>,
+    quirky_platforms: HashMap<String, QuirkyPlatform>,
+}
+
+pub fn sha1_hex(rom: &[u8]) -> String {
+    sha1_smol::Sha1::from(rom).digest().to_string()
+}
+
+pub fn lookup(sha1: &str) -> Option<RomInfo> {
+    DATABASE.iter().find_map(|program| {
+        let rom = program.roms.get(sha1)?.clone();
+        Some(RomInfo {
+            sha1: sha1.to_owned(),
+            title: program.title.clone(),
+            description: program.description.clone(),
+            release: program.release.clone(),
+            authors: program.authors.clone(),
+            file: rom.file,
+            platforms: rom.platforms,
+            tickrate: rom.tickrate,
+            colors: rom.colors.map(|colors| colors.pixels).unwrap_or_default(),
+            keys: rom.keys,
+            quirky_platforms: rom.quirky_platforms,
+        })
+    })
+}
+
+// The database's platform ids, mapped onto the nearest quirk set
+fn platform_quirks(id: &str) -> Option<Quirks> {
+    match id {
+        "originalChip8" | "hybridVIP" | "chip8x" => Some(Platform::Chip8.into()),
+        "modernChip8" => Some(Quirks {
+            memory_increment: true,
+            clipping: true,
+            ..Default::default()
+        }),
+        "chip48" | "superchip1" | "superchip" | "megachip8" => Some(Platform::SuperChip.into()),
+        "xochip" => Some(Platform::XoChip.into()),
+        _ => None,
+    }
+}
+
+impl RomInfo {
+    // Quirks of the first platform the ROM is listed for, with its exceptions
+    pub fn quirks(&self) -> Option<Quirks> {
+        let (id, quirks) = self
+            .platforms
+            .iter()
+            .find_map(|id| Some((id, platform_quirks(id)?)))?;
+        let Some(quirky) = self.quirky_platforms.get(id) else {
+            return Some(quirks);
+        };
+        let memory_increment = match (
+            quirky.memory_leave_i_unchanged,
+            quirky.memory_increment_by_x,
+        ) {
+            (Some(true), _) => Some(false),
+            (_, Some(by_x)) => Some(!by_x),
+            _ => None,
+        };
+        Some(Quirks {
+            vf_reset: quirky.logic.unwrap_or(quirks.vf_reset),
+            memory_increment: memory_increment.unwrap_or(quirks.memory_increment),
+            display_wait: quirky.vblank.unwrap_or(quirks.display_wait),
+            clipping: quirky.wrap.map_or(quirks.clipping, |wrap| !wrap),
+            shifting: quirky.shift.unwrap_or(quirks.shifting),
+            jumping: quirky.jump.unwrap_or(quirks.jumping),
+        })
+    }
+
+    // The bottom layer under the config file and command line
+    pub fn settings(&self) -> RomSettings {
+        let quirks = self.quirks().map(QuirkSettings::from).unwrap_or_default();
+        RomSettings {
+            quirks,
+            instructions_per_frame: self.tickrate,
+            palette: self.colors.join(",").parse().ok(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        database::{DATABASE, QuirkyPlatform, lookup, sha1_hex},
+        palette::Rgb,
+        quirks::{Platform, Quirks},
+    };
+
+    const IBM_LOGO: &[u8] = include_bytes!("../assets/roms/ibm.ch8");
+
+    #[test]
+    fn embedded_database_parses() {
+        assert!(!DATABASE.is_empty());
+    }
+
+    #[test]
+    fn finds_roms_by_sha1() {
+        let info = lookup(&sha1_hex(IBM_LOGO)).unwrap();
+
+        assert_eq!(info.title, "IBM Logo");
+        assert_eq!(info.quirks(), Some(Platform::Chip8.into()));
+        assert_eq!(info.settings().instructions_per_frame, Some(11));
+        let palette = info.settings().palette.unwrap();
+        assert_eq!(palette.background(), Rgb(0, 0, 0));
+        assert_eq!(palette.foreground(), Rgb(255, 255, 255));
+        assert!(lookup(&sha1_hex(b"not a rom")).is_none());
+    }
+
+    #[test]
+    fn quirky_platforms_override_the_preset() {
+        let mut info = lookup(&sha1_hex(IBM_LOGO)).unwrap();
+        info.platforms = vec!["megachip8".to_owned(), "superchip".to_owned()];
+        info.quirky_platforms = HashMap::from([(
+            "superchip".to_owned(),
+            QuirkyPlatform {
+                wrap: Some(true),
+                memory_increment_by_x: Some(false),
+                ..Default::default()
+            },
+        )]);
+
+        // megachip8 comes first and has no exceptions
+        assert_eq!(info.quirks(), Some(Platform::SuperChip.into()));
+
+        info.platforms.remove(0);
+        assert_eq!(
+            info.quirks(),
+            Some(Quirks {
+                clipping: false,
+                memory_increment: true,
+                ..Platform::SuperChip.into()
+            })
+        );
+    }
+}
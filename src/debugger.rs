@@ -0,0 +1,548 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::Cursor,
+    str::FromStr,
+};
+
+use anyhow::anyhow;
+
+use crate::{
+    Ahoy,
+    constants::MAX_MEMORY,
+    instructions::AhoyInstruction,
+    rewind::{apply_delta, encode_delta},
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Step,
+    Breakpoint(usize),
+    Watchpoint(usize),
+    Start,
+    CycleLimit,
+}
+
+// How far `continue` and `seek` run before giving control back
+const RESUME_LIMIT: u64 = 10_000_000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DebugCommand {
+    Step(u64),
+    Back(u64),
+    Continue,
+    ReverseContinue,
+    Seek(u64),
+    Break(usize),
+    Watch(usize),
+    Key(u8, bool),
+    Quit,
+}
+
+impl FromStr for DebugCommand {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = value.split_whitespace().collect();
+        let count = |word: Option<&&str>| match word {
+            Some(count) => count
+                .parse()
+                .map_err(|_| anyhow!("Expected a count, got {:?}", count)),
+            None => Ok(1),
+        };
+        let addr = |word: Option<&&str>| {
+            let word = word.ok_or_else(|| anyhow!("Expected an address"))?;
+            usize::from_str_radix(word.trim_start_matches("0x"), 16)
+                .map_err(|_| anyhow!("Expected a hex address, got {:?}", word))
+        };
+        match words.as_slice() {
+            ["s" | "step", rest @ ..] => Ok(DebugCommand::Step(count(rest.first())?)),
+            ["b" | "back", rest @ ..] => Ok(DebugCommand::Back(count(rest.first())?)),
+            ["c" | "continue"] => Ok(DebugCommand::Continue),
+            ["rc" | "reverse-continue"] => Ok(DebugCommand::ReverseContinue),
+            ["seek", cycle] => Ok(DebugCommand::Seek(count(Some(cycle))?)),
+            ["break", rest @ ..] => Ok(DebugCommand::Break(addr(rest.first())?)),
+            ["watch", rest @ ..] => Ok(DebugCommand::Watch(addr(rest.first())?)),
+            ["key", key, state @ ("down" | "up")] => {
+                let key = u8::from_str_radix(key, 16)
+                    .ok()
+                    .filter(|key| *key < 16)
+                    .ok_or_else(|| anyhow!("Expected a CHIP-8 key from 0 to F, got {:?}", key))?;
+                Ok(DebugCommand::Key(key, *state == "down"))
+            }
+            ["q" | "quit"] => Ok(DebugCommand::Quit),
+            _ => Err(anyhow!(
+                "Unknown command {:?}, expected step [N], back [N], continue, \
+                 reverse-continue, seek CYCLE, break ADDR, watch ADDR, key K down|up or quit",
+                value.trim()
+            )),
+        }
+    }
+}
+
+pub struct Debugger {
+    ahoy: Ahoy,
+    cycle: u64,
+    instructions_per_frame: u64,
+    checkpoint_interval: u64,
+    // The starting state in full, later checkpoints as deltas against it
+    origin: Vec<u8>,
+    checkpoints: BTreeMap<u64, Vec<u8>>,
+    checkpoints_size: usize,
+    budget: usize,
+    inputs: Vec<(u64, u8, bool)>,
+    breakpoints: HashSet<usize>,
+    watchpoints: HashSet<usize>,
+}
+
+impl Debugger {
+    pub fn new(
+        ahoy: Ahoy,
+        instructions_per_frame: usize,
+        checkpoint_interval: u64,
+        budget: usize,
+    ) -> anyhow::Result<Self> {
+        let mut origin = Vec::new();
+        ahoy.save_state(&mut origin)?;
+        Ok(Self {
+            origin,
+            ahoy,
+            cycle: 0,
+            instructions_per_frame: instructions_per_frame.max(1) as u64,
+            checkpoint_interval: checkpoint_interval.max(1),
+            checkpoints: BTreeMap::new(),
+            checkpoints_size: 0,
+            budget,
+            inputs: Vec::new(),
+            breakpoints: HashSet::new(),
+            watchpoints: HashSet::new(),
+        })
+    }
+
+    pub fn ahoy(&self) -> &Ahoy {
+        &self.ahoy
+    }
+
+    pub fn cycle(&self) -> u64 {
+        self.cycle
+    }
+
+    pub fn toggle_breakpoint(&mut self, addr: usize) -> bool {
+        if !self.breakpoints.remove(&addr) {
+            self.breakpoints.insert(addr);
+            return true;
+        }
+        false
+    }
+
+    pub fn toggle_watchpoint(&mut self, addr: usize) -> anyhow::Result<bool> {
+        if addr >= MAX_MEMORY {
+            return Err(anyhow!("Watchpoint 0x{:X} is outside of memory", addr));
+        }
+        if !self.watchpoints.remove(&addr) {
+            self.watchpoints.insert(addr);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    // Runs a command and describes where it stopped
+    pub fn apply(&mut self, command: &DebugCommand) -> anyhow::Result<String> {
+        let reason = match command {
+            DebugCommand::Step(count) => {
+                let mut reason = StopReason::Step;
+                for _ in 0..*count {
+                    reason = self.step()?;
+                    if reason != StopReason::Step {
+                        break;
+                    }
+                }
+                reason
+            }
+            DebugCommand::Back(count) => {
+                let mut reason = StopReason::Step;
+                for _ in 0..*count {
+                    reason = self.step_back()?;
+                    if reason != StopReason::Step {
+                        break;
+                    }
+                }
+                reason
+            }
+            DebugCommand::Continue => self.resume(RESUME_LIMIT)?,
+            DebugCommand::ReverseContinue => self.reverse_resume()?,
+            DebugCommand::Seek(cycle) => {
+                self.seek(*cycle)?;
+                StopReason::Step
+            }
+            DebugCommand::Break(addr) => {
+                let set = self.toggle_breakpoint(*addr);
+                return Ok(format!("Breakpoint at {:03X} {}", addr, on_off(set)));
+            }
+            DebugCommand::Watch(addr) => {
+                let set = self.toggle_watchpoint(*addr)?;
+                return Ok(format!("Watchpoint at {:03X} {}", addr, on_off(set)));
+            }
+            DebugCommand::Key(key, pressed) => {
+                self.set_key(*key, *pressed);
+                return Ok(format!(
+                    "Key {:X} {}",
+                    key,
+                    if *pressed { "down" } else { "up" }
+                ));
+            }
+            DebugCommand::Quit => return Ok(String::new()),
+        };
+        Ok(match reason {
+            StopReason::Step => self.summary(),
+            reason => format!("{:?}\n{}", reason, self.summary()),
+        })
+    }
+
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        // New input rewrites history, so anything recorded after it is stale
+        self.inputs.retain(|(cycle, _, _)| *cycle <= self.cycle);
+        let stale = self.checkpoints.split_off(&(self.cycle + 1));
+        self.checkpoints_size -= stale.values().map(Vec::len).sum::<usize>();
+        self.inputs.push((self.cycle, key, pressed));
+        self.ahoy.set_key(key, pressed);
+    }
+
+    pub fn step(&mut self) -> anyhow::Result<StopReason> {
+        let watched = self.watched_memory();
+        self.advance()?;
+        Ok(self.stop_reason(&watched).unwrap_or(StopReason::Step))
+    }
+
+    pub fn resume(&mut self, max_cycles: u64) -> anyhow::Result<StopReason> {
+        for _ in 0..max_cycles {
+            let watched = self.watched_memory();
+            self.advance()?;
+            if let Some(reason) = self.stop_reason(&watched) {
+                return Ok(reason);
+            }
+        }
+        Ok(StopReason::CycleLimit)
+    }
+
+    pub fn step_back(&mut self) -> anyhow::Result<StopReason> {
+        if self.cycle == 0 {
+            return Ok(StopReason::Start);
+        }
+        self.seek(self.cycle - 1)?;
+        Ok(StopReason::Step)
+    }
+
+    pub fn reverse_resume(&mut self) -> anyhow::Result<StopReason> {
+        let origin = self.cycle;
+        // Stops only inside (start, limit) are visible when replaying from a
+        // checkpoint, so each pass ends one cycle past the previous checkpoint
+        let mut limit = origin;
+        while limit > 1 {
+            let start = self.checkpoint_before(limit - 2);
+            self.seek(start)?;
+
+            let mut last_stop = None;
+            while self.cycle < limit - 1 {
+                let watched = self.watched_memory();
+                self.advance()?;
+                if let Some(reason) = self.stop_reason(&watched) {
+                    last_stop = Some((self.cycle, reason));
+                }
+            }
+            if let Some((cycle, reason)) = last_stop {
+                self.seek(cycle)?;
+                return Ok(reason);
+            }
+            limit = start + 1;
+        }
+
+        self.seek(0)?;
+        if origin > 0 && self.breakpoints.contains(&self.ahoy.counter) {
+            return Ok(StopReason::Breakpoint(self.ahoy.counter));
+        }
+        Ok(StopReason::Start)
+    }
+
+    pub fn seek(&mut self, target: u64) -> anyhow::Result<()> {
+        if target > self.cycle.saturating_add(RESUME_LIMIT) {
+            return Err(anyhow!(
+                "Cannot seek more than {} cycles ahead",
+                RESUME_LIMIT
+            ));
+        }
+        let start = self.checkpoint_before(target);
+        let state = match self.checkpoints.get(&start) {
+            Some(delta) => apply_delta(&self.origin, delta),
+            None => self.origin.clone(),
+        };
+        self.ahoy
+            .restore(Ahoy::load_state(&mut Cursor::new(state))?);
+        self.cycle = start;
+        while self.cycle < target {
+            self.advance()?;
+        }
+        self.apply_inputs();
+        Ok(())
+    }
+
+    fn checkpoint_before(&self, target: u64) -> u64 {
+        self.checkpoints
+            .range(..=target)
+            .next_back()
+            .map_or(0, |(cycle, _)| *cycle)
+    }
+
+    fn advance(&mut self) -> anyhow::Result<()> {
+        self.apply_inputs();
+        self.ahoy.process()?;
+        self.cycle += 1;
+
+        if self.cycle.is_multiple_of(self.instructions_per_frame) {
+            self.ahoy.tick_timers();
+        }
+        if self.cycle.is_multiple_of(self.checkpoint_interval)
+            && !self.checkpoints.contains_key(&self.cycle)
+        {
+            self.checkpoint()?;
+        }
+        Ok(())
+    }
+
+    // Over budget the oldest checkpoints go first, so seeking far back
+    // re-executes from further away instead of failing
+    fn checkpoint(&mut self) -> anyhow::Result<()> {
+        let mut state = Vec::new();
+        self.ahoy.save_state(&mut state)?;
+        let delta = encode_delta(&self.origin, &state);
+        self.checkpoints_size += delta.len();
+        self.checkpoints.insert(self.cycle, delta);
+
+        while self.used_bytes() > self.budget {
+            match self.checkpoints.pop_first() {
+                Some((_, oldest)) => self.checkpoints_size -= oldest.len(),
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.origin.len() + self.checkpoints_size
+    }
+
+    // One line of machine state for the command line debugger
+    pub fn summary(&self) -> String {
+        let ahoy = &self.ahoy;
+        let opcode = u16::from_be_bytes([
+            ahoy.memory[ahoy.counter],
+            ahoy.memory[(ahoy.counter + 1) % MAX_MEMORY],
+        ]);
+        let registers: Vec<String> = ahoy
+            .registers
+            .iter()
+            .enumerate()
+            .map(|(register, value)| format!("V{register:X}={value:02X}"))
+            .collect();
+        format!(
+            "cycle {} PC={:03X} {:04X} {:?} I={:03X} SP={} DT={:02X} ST={:02X} {}",
+            self.cycle,
+            ahoy.counter,
+            opcode,
+            AhoyInstruction::from(opcode),
+            ahoy.index,
+            ahoy.stack.len(),
+            ahoy.delay_timer,
+            ahoy.sound_timer,
+            registers.join(" ")
+        )
+    }
+
+    fn apply_inputs(&mut self) {
+        for (_, key, pressed) in self.inputs.iter().filter(|(c, _, _)| *c == self.cycle) {
+            self.ahoy.set_key(*key, *pressed);
+        }
+    }
+
+    fn watched_memory(&self) -> Vec<(usize, u8)> {
+        self.watchpoints
+            .iter()
+            .map(|addr| (*addr, self.ahoy.memory[*addr]))
+            .collect()
+    }
+
+    fn stop_reason(&self, watched: &[(usize, u8)]) -> Option<StopReason> {
+        if let Some((addr, _)) = watched
+            .iter()
+            .find(|(addr, value)| self.ahoy.memory[*addr] != *value)
+        {
+            return Some(StopReason::Watchpoint(*addr));
+        }
+        self.breakpoints
+            .contains(&self.ahoy.counter)
+            .then_some(StopReason::Breakpoint(self.ahoy.counter))
+    }
+}
+
+fn on_off(set: bool) -> &'static str {
+    if set { "set" } else { "cleared" }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Ahoy,
+        debugger::{DebugCommand, Debugger, StopReason},
+    };
+
+    // Counts up in V0 and loops forever: 0x200 ADD V0,1 / 0x202 RND V1 / 0x204 JP 0x200
+    fn counting_ahoy() -> Ahoy {
+        let mut ahoy = Ahoy::default();
+        ahoy.memory[0x200..0x206].copy_from_slice(&[0x70, 0x01, 0xC1, 0xFF, 0x12, 0x00]);
+        ahoy
+    }
+
+    #[test]
+    fn step_back_restores_the_previous_instruction_state() {
+        let mut debugger = Debugger::new(counting_ahoy(), 11, 4, usize::MAX).unwrap();
+        let mut history = vec![debugger.ahoy().clone()];
+        for _ in 0..20 {
+            debugger.step().unwrap();
+            history.push(debugger.ahoy().clone());
+        }
+
+        for expected in history.iter().rev().skip(1) {
+            debugger.step_back().unwrap();
+            assert_eq!(debugger.ahoy().registers, expected.registers);
+            assert_eq!(debugger.ahoy().counter, expected.counter);
+            assert_eq!(debugger.ahoy().rng_state, expected.rng_state);
+        }
+        assert_eq!(debugger.cycle(), 0);
+        assert_eq!(debugger.step_back().unwrap(), StopReason::Start);
+    }
+
+    #[test]
+    fn resume_stops_at_breakpoints() {
+        let mut debugger = Debugger::new(counting_ahoy(), 11, 4, usize::MAX).unwrap();
+        debugger.toggle_breakpoint(0x204);
+
+        assert_eq!(debugger.resume(100).unwrap(), StopReason::Breakpoint(0x204));
+        assert_eq!(debugger.cycle(), 2);
+        assert_eq!(debugger.resume(100).unwrap(), StopReason::Breakpoint(0x204));
+        assert_eq!(debugger.cycle(), 5);
+    }
+
+    #[test]
+    fn reverse_resume_stops_at_the_previous_breakpoint() {
+        let mut debugger = Debugger::new(counting_ahoy(), 11, 4, usize::MAX).unwrap();
+        for _ in 0..30 {
+            debugger.step().unwrap();
+        }
+        debugger.toggle_breakpoint(0x204);
+
+        assert_eq!(
+            debugger.reverse_resume().unwrap(),
+            StopReason::Breakpoint(0x204)
+        );
+        assert_eq!(debugger.cycle(), 29);
+        assert_eq!(
+            debugger.reverse_resume().unwrap(),
+            StopReason::Breakpoint(0x204)
+        );
+        assert_eq!(debugger.cycle(), 26);
+        assert_eq!(debugger.ahoy().registers[0], 9);
+    }
+
+    #[test]
+    fn reverse_resume_returns_to_the_start_without_stops() {
+        let mut debugger = Debugger::new(counting_ahoy(), 11, 4, usize::MAX).unwrap();
+        for _ in 0..10 {
+            debugger.step().unwrap();
+        }
+
+        assert_eq!(debugger.reverse_resume().unwrap(), StopReason::Start);
+        assert_eq!(debugger.cycle(), 0);
+        assert_eq!(debugger.ahoy().registers[0], 0);
+    }
+
+    #[test]
+    fn replay_applies_logged_inputs() {
+        let mut debugger = Debugger::new(counting_ahoy(), 11, 4, usize::MAX).unwrap();
+        for _ in 0..5 {
+            debugger.step().unwrap();
+        }
+        debugger.set_key(0x7, true);
+        for _ in 0..5 {
+            debugger.step().unwrap();
+        }
+
+        debugger.seek(4).unwrap();
+        assert_eq!(debugger.ahoy().keypad(), 0);
+        debugger.seek(7).unwrap();
+        assert_eq!(debugger.ahoy().keypad(), 1 << 0x7);
+    }
+
+    #[test]
+    fn seek_rejects_targets_too_far_ahead() {
+        let mut debugger = Debugger::new(counting_ahoy(), 11, 4, usize::MAX).unwrap();
+        debugger.step().unwrap();
+
+        assert!(debugger.seek(u64::MAX).is_err());
+        assert_eq!(debugger.cycle(), 1);
+    }
+
+    #[test]
+    fn watchpoints_outside_memory_are_rejected() {
+        let mut debugger = Debugger::new(counting_ahoy(), 11, 4, usize::MAX).unwrap();
+
+        assert!(debugger.toggle_watchpoint(0x1000).is_err());
+        assert!(debugger.toggle_watchpoint(0xFFF).unwrap());
+        assert_eq!(debugger.resume(20).unwrap(), StopReason::CycleLimit);
+    }
+
+    #[test]
+    fn checkpoints_stay_within_budget_and_seek_still_works() {
+        let mut state = Vec::new();
+        counting_ahoy().save_state(&mut state).unwrap();
+        let budget = state.len() + 256;
+        let mut debugger = Debugger::new(counting_ahoy(), 11, 1, budget).unwrap();
+        for _ in 0..300 {
+            debugger.step().unwrap();
+            assert!(debugger.used_bytes() <= budget);
+        }
+
+        // Cycle 2 lost its checkpoint and is re-executed from the start
+        debugger.seek(2).unwrap();
+        assert_eq!(debugger.ahoy().registers[0], 1);
+        assert_eq!(debugger.ahoy().counter, 0x204);
+    }
+
+    #[test]
+    fn parses_debugger_commands() {
+        assert_eq!("s".parse::<DebugCommand>().unwrap(), DebugCommand::Step(1));
+        assert_eq!(
+            "back 10".parse::<DebugCommand>().unwrap(),
+            DebugCommand::Back(10)
+        );
+        assert_eq!(
+            "break 0x2A4".parse::<DebugCommand>().unwrap(),
+            DebugCommand::Break(0x2A4)
+        );
+        assert_eq!(
+            "key c down".parse::<DebugCommand>().unwrap(),
+            DebugCommand::Key(0xC, true)
+        );
+        assert!("key 10 down".parse::<DebugCommand>().is_err());
+        assert!("jump".parse::<DebugCommand>().is_err());
+    }
+
+    #[test]
+    fn commands_report_where_they_stopped() {
+        let mut debugger = Debugger::new(counting_ahoy(), 11, 4, usize::MAX).unwrap();
+        debugger.apply(&DebugCommand::Break(0x204)).unwrap();
+
+        let stopped = debugger.apply(&DebugCommand::Continue).unwrap();
+        assert!(stopped.starts_with("Breakpoint(516)"));
+        assert!(stopped.contains("PC=204"));
+        assert!(debugger.apply(&DebugCommand::Watch(0x1000)).is_err());
+    }
+}
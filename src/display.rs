@@ -1,6 +1,22 @@
+use std::{io::Write, str::FromStr};
+
+use anyhow::anyhow;
+use crossterm::{
+    execute,
+    terminal::{self, EnterAlternateScreen},
+};
 use ratatui::{
-    style::Color,
-    widgets::canvas::{Canvas, Rectangle},
+    Terminal,
+    backend::CrosstermBackend,
+    buffer::Buffer,
+    layout::{Alignment, Rect},
+    style::{Modifier, Style},
+    widgets::{Block, Clear, Paragraph, Widget, Wrap},
+};
+
+use crate::{
+    filter::Glow,
+    palette::{ColorDepth, Palette},
 };
 
 pub const DISPLAY_WIDTH: usize = 64;
@@ -8,84 +24,709 @@ pub const DISPLAY_HEIGHT: usize = 32;
 pub const SPRITE_WIDTH: usize = 8;
 pub type AhoyFrame = [u64; DISPLAY_HEIGHT];
 
+// FNV-1a over the rows, stable across platforms and releases
+pub fn frame_hash(frame: &AhoyFrame) -> u64 {
+    frame
+        .iter()
+        .flat_map(|row| row.to_le_bytes())
+        .fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+        })
+}
+
+pub fn pixel(frame: &AhoyFrame, x: usize, y: usize) -> bool {
+    (frame[y] >> (DISPLAY_WIDTH - 1 - x)) & 0b1 == 1
+}
+
 pub trait AhoyDisplay {
     fn draw(&mut self, frame: &AhoyFrame) -> anyhow::Result<()>;
+
+    // Backends without partial brightness show any glowing pixel as lit
+    fn draw_glow(&mut self, glow: &Glow) -> anyhow::Result<()> {
+        self.draw(&glow.threshold())
+    }
+
+    // Short text describing what the emulator is doing, for displays with room for it
+    fn set_status(&mut self, _status: &str) {}
+
+    // Lines boxed over the picture, e.g. while paused; empty hides the box
+    fn set_overlay(&mut self, _lines: &[String]) {}
+
+    // Shows the keypad beside the picture, or hides it for None
+    fn set_keypad(&mut self, _keypad: Option<KeypadView>) {}
 }
 
-pub struct RatatuiAhoyDisplay {
-    terminal: ratatui::DefaultTerminal,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Renderer {
+    Block,
+    HalfBlock,
+    Braille,
 }
-impl Default for RatatuiAhoyDisplay {
-    fn default() -> Self {
-        Self {
-            terminal: ratatui::init(),
+
+impl Renderer {
+    const ALL: [Renderer; 3] = [Renderer::Block, Renderer::HalfBlock, Renderer::Braille];
+
+    // Dots packed into a single terminal cell
+    pub fn cell_pixels(self) -> (usize, usize) {
+        match self {
+            Renderer::Block => (1, 1),
+            Renderer::HalfBlock => (1, 2),
+            Renderer::Braille => (2, 4),
+        }
+    }
+
+    // Cells are about twice as tall as wide, so full blocks need two dots per pixel
+    fn dots_per_pixel_wide(self) -> usize {
+        match self {
+            Renderer::Block => 2,
+            Renderer::HalfBlock | Renderer::Braille => 1,
         }
     }
 }
-impl Drop for RatatuiAhoyDisplay {
-    fn drop(&mut self) {
-        ratatui::restore();
+
+impl FromStr for Renderer {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "block" => Ok(Renderer::Block),
+            "half-block" => Ok(Renderer::HalfBlock),
+            "braille" => Ok(Renderer::Braille),
+            _ => Err(anyhow!(
+                "Unknown renderer {:?}, expected block, half-block or braille",
+                value
+            )),
+        }
     }
 }
-struct Size {
-    width: f64,
-    height: f64,
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub renderer: Renderer,
+    pub scale: usize,
 }
-impl Size {
-    fn new(width: f64, height: f64) -> Self {
-        Self { width, height }
+
+impl Viewport {
+    pub fn cells(self) -> (u16, u16) {
+        let (width, height) = self.renderer.cell_pixels();
+        let dots_wide = DISPLAY_WIDTH * self.scale * self.renderer.dots_per_pixel_wide();
+        (
+            dots_wide.div_ceil(width) as u16,
+            (DISPLAY_HEIGHT * self.scale).div_ceil(height) as u16,
+        )
+    }
+
+    // The largest integer scale that fits, preferring less dense renderers on
+    // ties as denser glyphs look noisier
+    pub fn fit(width: u16, height: u16, renderer: Option<Renderer>) -> Option<Self> {
+        let renderers = match renderer {
+            Some(renderer) => vec![renderer],
+            None => Renderer::ALL.to_vec(),
+        };
+        let fits = |viewport: &Viewport| {
+            let (cells_wide, cells_high) = viewport.cells();
+            cells_wide <= width && cells_high <= height
+        };
+
+        renderers
+            .into_iter()
+            .filter_map(|renderer| {
+                (1..)
+                    .map(|scale| Viewport { renderer, scale })
+                    .take_while(fits)
+                    .last()
+            })
+            .fold(None, |best: Option<Viewport>, viewport| match best {
+                Some(best) if best.cells().0 >= viewport.cells().0 => Some(best),
+                _ => Some(viewport),
+            })
     }
-    fn scale(self, factor: f64) -> Self {
-        Self {
-            width: self.width * factor,
-            height: self.height * factor,
+
+    pub fn minimum(renderer: Option<Renderer>) -> (u16, u16) {
+        Viewport {
+            renderer: renderer.unwrap_or(Renderer::Braille),
+            scale: 1,
         }
+        .cells()
+    }
+
+    // Centres the image inside `area`, leaving letterbox bars around it
+    pub fn centered(self, area: Rect) -> Rect {
+        let (width, height) = self.cells();
+        let (width, height) = (width.min(area.width), height.min(area.height));
+        Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        )
     }
 }
-impl Default for Size {
-    fn default() -> Self {
-        Self {
-            width: 1.0,
-            height: 1.0,
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Glyph {
+    pub symbol: char,
+    pub foreground: u8,
+    pub background: u8,
+}
+
+pub fn glyph(glow: &Glow, viewport: Viewport, cell_x: usize, cell_y: usize) -> Glyph {
+    let (width, height) = viewport.renderer.cell_pixels();
+    let pixel_width = viewport.scale * viewport.renderer.dots_per_pixel_wide();
+    let lit = |dx: usize, dy: usize| {
+        let (x, y) = (
+            (cell_x * width + dx) / pixel_width,
+            (cell_y * height + dy) / viewport.scale,
+        );
+        if x < DISPLAY_WIDTH && y < DISPLAY_HEIGHT {
+            glow.at(x, y)
+        } else {
+            0
+        }
+    };
+
+    match viewport.renderer {
+        Renderer::Block => Glyph {
+            symbol: '█',
+            foreground: lit(0, 0),
+            background: 0,
+        },
+        // The upper half takes the foreground colour, the lower half the background
+        Renderer::HalfBlock => Glyph {
+            symbol: '▀',
+            foreground: lit(0, 0),
+            background: lit(0, 1),
+        },
+        Renderer::Braille => {
+            const DOTS: [(usize, usize, u32); 8] = [
+                (0, 0, 0x01),
+                (0, 1, 0x02),
+                (0, 2, 0x04),
+                (1, 0, 0x08),
+                (1, 1, 0x10),
+                (1, 2, 0x20),
+                (0, 3, 0x40),
+                (1, 3, 0x80),
+            ];
+            // A cell has one colour, so its dots share the brightest one
+            let (dots, brightness) = DOTS
+                .iter()
+                .map(|(dx, dy, bit)| (bit, lit(*dx, *dy)))
+                .filter(|(_, brightness)| *brightness > 0)
+                .fold((0, 0), |(dots, brightest), (bit, brightness)| {
+                    (dots | bit, brightest.max(brightness))
+                });
+            Glyph {
+                symbol: char::from_u32(0x2800 + dots).unwrap_or(' '),
+                foreground: brightness,
+                background: 0,
+            }
         }
     }
 }
 
+pub struct FrameWidget<'a> {
+    pub glow: &'a Glow,
+    pub viewport: Viewport,
+    pub palette: &'a Palette,
+    pub depth: ColorDepth,
+}
+
+impl Widget for FrameWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let color = |brightness: u8| self.palette.shade(brightness, self.depth);
+        let (cells_wide, cells_high) = self.viewport.cells();
+
+        for cell_y in 0..cells_high.min(area.height) {
+            for cell_x in 0..cells_wide.min(area.width) {
+                let glyph = glyph(self.glow, self.viewport, cell_x as usize, cell_y as usize);
+                buf[(area.x + cell_x, area.y + cell_y)]
+                    .set_char(glyph.symbol)
+                    .set_fg(color(glyph.foreground))
+                    .set_bg(color(glyph.background));
+            }
+        }
+    }
+}
+
+// The COSMAC VIP layout, row by row
+const KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+const KEYPAD_KEY_WIDTH: u16 = 5;
+pub const KEYPAD_PANEL_SIZE: (u16, u16) = (KEYPAD_KEY_WIDTH * 4 + 2, 4 + 2);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeypadView {
+    // Host key for each CHIP-8 key
+    pub labels: [char; 16],
+    pub pressed: u16,
+    // Register an FX0A is waiting to fill
+    pub awaiting: Option<usize>,
+}
+
+pub struct KeypadWidget<'a> {
+    pub keypad: &'a KeypadView,
+}
+
+impl Widget for KeypadWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut border = Block::bordered().title(" keypad ");
+        if let Some(register_addr) = self.keypad.awaiting {
+            border = border
+                .title_bottom(format!(" waiting for key V{:X} ", register_addr))
+                .border_style(Style::new().add_modifier(Modifier::BOLD));
+        }
+        let inner = border.inner(area);
+        border.render(area, buf);
+
+        for (row, keys) in KEYPAD_LAYOUT.iter().enumerate() {
+            for (column, key) in keys.iter().enumerate() {
+                let cell = Rect {
+                    x: inner.x + column as u16 * KEYPAD_KEY_WIDTH,
+                    y: inner.y + row as u16,
+                    width: KEYPAD_KEY_WIDTH,
+                    height: 1,
+                }
+                .intersection(inner);
+                let style = if self.keypad.pressed & (1 << key) != 0 {
+                    Style::new().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::new()
+                };
+                let label = self.keypad.labels[*key as usize].to_ascii_uppercase();
+                Paragraph::new(format!("{:X}:{}", key, label))
+                    .alignment(Alignment::Center)
+                    .style(style)
+                    .render(cell, buf);
+            }
+        }
+    }
+}
+
+// The framed, letterboxed screen with the ROM name on top and status below
+pub struct ScreenWidget<'a> {
+    pub glow: &'a Glow,
+    pub renderer: Option<Renderer>,
+    pub palette: &'a Palette,
+    pub depth: ColorDepth,
+    pub title: &'a str,
+    pub status: &'a str,
+    pub overlay: &'a [String],
+    pub keypad: Option<&'a KeypadView>,
+}
+
+impl Widget for ScreenWidget<'_> {
+    fn render(self, mut area: Rect, buf: &mut Buffer) {
+        if let Some(keypad) = self.keypad {
+            let (width, height) = KEYPAD_PANEL_SIZE;
+            let width = width.min(area.width);
+            area.width -= width;
+            let panel = Rect {
+                x: area.right(),
+                width,
+                height: height.min(area.height),
+                ..area
+            };
+            KeypadWidget { keypad }.render(panel, buf);
+        }
+        let border = Block::bordered()
+            .title(format!(" {} ", self.title))
+            .title_bottom(format!(" {} ", self.status));
+        let inner = border.inner(area);
+        border.render(area, buf);
+
+        match Viewport::fit(inner.width, inner.height, self.renderer) {
+            Some(viewport) => FrameWidget {
+                glow: self.glow,
+                viewport,
+                palette: self.palette,
+                depth: self.depth,
+            }
+            .render(viewport.centered(inner), buf),
+            None => {
+                let (width, height) = Viewport::minimum(self.renderer);
+                let message = format!(
+                    "Terminal too small, need at least {}x{}",
+                    width + 2,
+                    height + 2
+                );
+                let lower_half = Rect {
+                    y: inner.y + inner.height / 2,
+                    height: inner.height - inner.height / 2,
+                    ..inner
+                };
+                Paragraph::new(message)
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: true })
+                    .render(lower_half, buf);
+            }
+        }
+
+        if !self.overlay.is_empty() {
+            let widest = self.overlay.iter().map(|line| line.chars().count());
+            let width = (widest.max().unwrap_or(0) as u16 + 2).min(inner.width);
+            let height = (self.overlay.len() as u16 + 2).min(inner.height);
+            let corner = Rect {
+                x: inner.right() - width,
+                width,
+                height,
+                ..inner
+            };
+            Clear.render(corner, buf);
+            Paragraph::new(self.overlay.join("\n"))
+                .block(Block::bordered())
+                .render(corner, buf);
+        }
+    }
+}
+
+pub struct RatatuiAhoyDisplay {
+    terminal: Terminal<CrosstermBackend<Box<dyn Write>>>,
+    renderer: Option<Renderer>,
+    palette: Palette,
+    depth: ColorDepth,
+    title: String,
+    status: String,
+    overlay: Vec<String>,
+    keypad: Option<KeypadView>,
+}
+impl RatatuiAhoyDisplay {
+    // Draws through `output`, which is normally stdout but may tee it
+    // elsewhere. A `None` renderer is picked from the terminal size on every draw
+    pub fn new(
+        mut output: Box<dyn Write>,
+        renderer: Option<Renderer>,
+        palette: Palette,
+        title: &str,
+    ) -> anyhow::Result<Self> {
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            ratatui::restore();
+            hook(info);
+        }));
+        terminal::enable_raw_mode()?;
+        execute!(output, EnterAlternateScreen)?;
+
+        Ok(Self {
+            terminal: Terminal::new(CrosstermBackend::new(output))?,
+            renderer,
+            palette,
+            depth: ColorDepth::detect(),
+            title: title.to_owned(),
+            status: String::new(),
+            overlay: Vec::new(),
+            keypad: None,
+        })
+    }
+}
+impl Drop for RatatuiAhoyDisplay {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
 impl AhoyDisplay for RatatuiAhoyDisplay {
+    fn set_status(&mut self, status: &str) {
+        status.clone_into(&mut self.status);
+    }
+
+    fn set_overlay(&mut self, lines: &[String]) {
+        lines.clone_into(&mut self.overlay);
+    }
+
+    fn set_keypad(&mut self, keypad: Option<KeypadView>) {
+        self.keypad = keypad;
+    }
+
     fn draw(&mut self, frame: &AhoyFrame) -> anyhow::Result<()> {
-        let rectangle_size = Size::new(1.0, 1.0);
-        let display_size = Size::new(
-            DISPLAY_WIDTH as f64 * rectangle_size.width * 1.0,
-            DISPLAY_HEIGHT as f64 * rectangle_size.height * 1.0,
-        );
+        self.draw_glow(&Glow::from_frame(frame))
+    }
+
+    fn draw_glow(&mut self, glow: &Glow) -> anyhow::Result<()> {
         self.terminal.draw(|ratatui_frame| {
-            let area = ratatui_frame.area();
             ratatui_frame.render_widget(
-                Canvas::default()
-                    .marker(ratatui::symbols::Marker::Block)
-                    .paint(|ctx| {
-                        for (row_number, row) in frame.iter().rev().enumerate() {
-                            for col in 0..DISPLAY_WIDTH {
-                                let pixel = row >> col;
-                                ctx.draw(&Rectangle {
-                                    x: (rectangle_size.width * (DISPLAY_WIDTH - 1 - col) as f64),
-                                    y: (rectangle_size.height * row_number as f64),
-                                    width: rectangle_size.width,
-                                    height: rectangle_size.height,
-                                    color: if pixel & 0b1 == 1 {
-                                        Color::White
-                                    } else {
-                                        Color::Black
-                                    },
-                                });
-                            }
-                        }
-                    })
-                    .x_bounds([0.0, display_size.width])
-                    .y_bounds([0.0, display_size.height]),
-                area,
+                ScreenWidget {
+                    glow,
+                    renderer: self.renderer,
+                    palette: &self.palette,
+                    depth: self.depth,
+                    title: &self.title,
+                    status: &self.status,
+                    overlay: &self.overlay,
+                    keypad: self.keypad.as_ref(),
+                },
+                ratatui_frame.area(),
             );
         })?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{
+        buffer::Buffer,
+        layout::Rect,
+        style::{Color, Modifier},
+        widgets::Widget,
+    };
+
+    use crate::{
+        display::{
+            DISPLAY_HEIGHT, FrameWidget, Glyph, KeypadView, Renderer, ScreenWidget, Viewport, glyph,
+        },
+        filter::Glow,
+        palette::{ColorDepth, Palette},
+    };
+
+    fn checkered_corner() -> Glow {
+        let mut frame = [0; DISPLAY_HEIGHT];
+        frame[0] = 0b10 << 62;
+        frame[1] = 0b01 << 62;
+        frame[2] = 0b10 << 62;
+        frame[3] = 0b11 << 62;
+        Glow::from_frame(&frame)
+    }
+
+    fn viewport(renderer: Renderer, scale: usize) -> Viewport {
+        Viewport { renderer, scale }
+    }
+
+    fn row(buffer: &Buffer, y: u16) -> String {
+        (0..buffer.area.width)
+            .map(|x| buffer[(x, y)].symbol())
+            .collect()
+    }
+
+    #[test]
+    fn viewports_keep_pixels_square() {
+        assert_eq!(viewport(Renderer::Block, 1).cells(), (128, 32));
+        assert_eq!(viewport(Renderer::HalfBlock, 1).cells(), (64, 16));
+        assert_eq!(viewport(Renderer::HalfBlock, 3).cells(), (192, 48));
+        assert_eq!(viewport(Renderer::Braille, 1).cells(), (32, 8));
+    }
+
+    #[test]
+    fn fit_picks_the_largest_integer_scale() {
+        assert_eq!(
+            Viewport::fit(200, 60, None),
+            Some(viewport(Renderer::HalfBlock, 3))
+        );
+        assert_eq!(
+            Viewport::fit(78, 22, None),
+            Some(viewport(Renderer::HalfBlock, 1))
+        );
+        assert_eq!(
+            Viewport::fit(40, 10, None),
+            Some(viewport(Renderer::Braille, 1))
+        );
+        assert_eq!(Viewport::fit(10, 4, None), None);
+    }
+
+    #[test]
+    fn fit_prefers_less_dense_renderers_on_ties() {
+        assert_eq!(
+            Viewport::fit(128, 32, None),
+            Some(viewport(Renderer::Block, 1))
+        );
+        assert_eq!(Viewport::fit(100, 40, Some(Renderer::Block)), None);
+    }
+
+    #[test]
+    fn centered_letterboxes_the_image() {
+        let area = viewport(Renderer::HalfBlock, 1).centered(Rect::new(1, 1, 78, 22));
+
+        assert_eq!(area, Rect::new(8, 4, 64, 16));
+    }
+
+    #[test]
+    fn half_block_splits_a_cell_between_two_rows() {
+        let frame = checkered_corner();
+
+        assert_eq!(
+            glyph(&frame, viewport(Renderer::HalfBlock, 1), 0, 0),
+            Glyph {
+                symbol: '▀',
+                foreground: 255,
+                background: 0
+            }
+        );
+        assert_eq!(
+            glyph(&frame, viewport(Renderer::HalfBlock, 1), 1, 1),
+            Glyph {
+                symbol: '▀',
+                foreground: 0,
+                background: 255
+            }
+        );
+    }
+
+    #[test]
+    fn scaling_repeats_each_pixel() {
+        let frame = checkered_corner();
+        let scaled = viewport(Renderer::HalfBlock, 2);
+
+        // Pixel (0, 0) covers two cells across and one full cell down
+        for cell_x in 0..2 {
+            let lit = glyph(&frame, scaled, cell_x, 0);
+            assert_eq!((lit.foreground, lit.background), (255, 255));
+        }
+        assert_eq!(glyph(&frame, scaled, 2, 0).foreground, 0);
+    }
+
+    #[test]
+    fn braille_sets_one_dot_per_pixel() {
+        let frame = checkered_corner();
+
+        // Dots 1, 3, 5, 7 and 8 of the first 2x4 block
+        assert_eq!(
+            glyph(&frame, viewport(Renderer::Braille, 1), 0, 0).symbol,
+            '\u{28D5}'
+        );
+        assert_eq!(
+            glyph(&frame, viewport(Renderer::Braille, 1), 1, 0).symbol,
+            '\u{2800}'
+        );
+    }
+
+    #[test]
+    fn widget_writes_glyphs_into_the_buffer() {
+        let frame = checkered_corner();
+        let area = Rect::new(0, 0, 32, 8);
+        let mut buffer = Buffer::empty(area);
+
+        FrameWidget {
+            glow: &frame,
+            viewport: viewport(Renderer::Braille, 1),
+            palette: &Palette::default(),
+            depth: ColorDepth::Ansi16,
+        }
+        .render(area, &mut buffer);
+
+        assert_eq!(buffer[(0, 0)].symbol(), "\u{28D5}");
+        assert_eq!(buffer[(0, 0)].fg, Color::White);
+        assert_eq!(buffer[(31, 7)].symbol(), "\u{2800}");
+    }
+
+    #[test]
+    fn widget_colours_pixels_from_the_palette() {
+        let frame = checkered_corner();
+        let area = Rect::new(0, 0, 64, 16);
+        let mut buffer = Buffer::empty(area);
+
+        FrameWidget {
+            glow: &frame,
+            viewport: viewport(Renderer::HalfBlock, 1),
+            palette: &"amber".parse().unwrap(),
+            depth: ColorDepth::TrueColor,
+        }
+        .render(area, &mut buffer);
+
+        assert_eq!(buffer[(0, 0)].fg, Color::Rgb(0xFF, 0xB0, 0x00));
+        assert_eq!(buffer[(0, 0)].bg, Color::Rgb(0x1A, 0x0F, 0x00));
+    }
+
+    #[test]
+    fn screen_frames_the_image_with_title_and_status() {
+        let frame = checkered_corner();
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 80, 24));
+
+        ScreenWidget {
+            glow: &frame,
+            renderer: None,
+            palette: &Palette::default(),
+            depth: ColorDepth::TrueColor,
+            title: "ibm.ch8",
+            status: "running",
+            overlay: &[],
+            keypad: None,
+        }
+        .render(buffer.area, &mut buffer);
+
+        assert!(row(&buffer, 0).starts_with("┌ ibm.ch8 ─"));
+        assert!(row(&buffer, 23).starts_with("└ running ─"));
+        assert_eq!(buffer[(8, 4)].symbol(), "▀");
+        assert_eq!(buffer[(8, 4)].fg, Color::Rgb(255, 255, 255));
+        assert_eq!(buffer[(8, 4)].bg, Color::Rgb(0, 0, 0));
+        assert_eq!(buffer[(7, 4)].symbol(), " ");
+    }
+
+    #[test]
+    fn screen_explains_when_the_terminal_is_too_small() {
+        let frame = checkered_corner();
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 50, 7));
+
+        ScreenWidget {
+            glow: &frame,
+            renderer: None,
+            palette: &Palette::default(),
+            depth: ColorDepth::TrueColor,
+            title: "ibm.ch8",
+            status: "running",
+            overlay: &[],
+            keypad: None,
+        }
+        .render(buffer.area, &mut buffer);
+
+        assert!(row(&buffer, 3).contains("Terminal too small, need at least 34x10"));
+    }
+
+    #[test]
+    fn screen_boxes_the_overlay_in_the_top_right() {
+        let frame = checkered_corner();
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 80, 24));
+
+        ScreenWidget {
+            glow: &frame,
+            renderer: None,
+            palette: &Palette::default(),
+            depth: ColorDepth::TrueColor,
+            title: "ibm.ch8",
+            status: "running",
+            overlay: &["Paused".to_owned(), "Esc quit".to_owned()],
+            keypad: None,
+        }
+        .render(buffer.area, &mut buffer);
+
+        assert!(row(&buffer, 1).ends_with("┌────────┐│"));
+        assert!(row(&buffer, 2).ends_with("│Paused  ││"));
+        assert!(row(&buffer, 3).ends_with("│Esc quit││"));
+        assert_eq!(buffer[(8, 4)].symbol(), "▀");
+    }
+
+    #[test]
+    fn screen_puts_the_keypad_beside_the_image() {
+        let frame = checkered_corner();
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 80, 24));
+        let keypad = KeypadView {
+            labels: [
+                'x', '1', '2', '3', 'q', 'w', 'e', 'a', 's', 'd', 'z', 'c', '4', 'r', 'f', 'v',
+            ],
+            pressed: 1 << 0x5,
+            awaiting: Some(0x3),
+        };
+
+        ScreenWidget {
+            glow: &frame,
+            renderer: None,
+            palette: &Palette::default(),
+            depth: ColorDepth::TrueColor,
+            title: "ibm.ch8",
+            status: "running",
+            overlay: &[],
+            keypad: Some(&keypad),
+        }
+        .render(buffer.area, &mut buffer);
+
+        assert!(row(&buffer, 0).ends_with("┐┌ keypad ────────────┐"));
+        assert!(row(&buffer, 1).ends_with("││ 1:1  2:2  3:3  C:4 │"));
+        assert!(row(&buffer, 4).ends_with("││ A:Z  0:X  B:C  F:V │"));
+        assert!(row(&buffer, 5).ends_with("│└ waiting for key V3 ┘"));
+        assert!(buffer[(66, 2)].modifier.contains(Modifier::REVERSED));
+        assert!(!buffer[(61, 2)].modifier.contains(Modifier::REVERSED));
+    }
+}
@@ -0,0 +1,265 @@
+use std::io::Write;
+
+use anyhow::anyhow;
+
+use crate::{
+    display::{AhoyFrame, DISPLAY_HEIGHT, DISPLAY_WIDTH, pixel},
+    palette::{Palette, Rgb},
+};
+
+pub fn write_ascii<W: Write>(frame: &AhoyFrame, writer: &mut W) -> anyhow::Result<()> {
+    for y in 0..DISPLAY_HEIGHT {
+        let line: String = (0..DISPLAY_WIDTH)
+            .map(|x| if pixel(frame, x, y) { '#' } else { '.' })
+            .collect();
+        writeln!(writer, "{line}")?;
+    }
+    Ok(())
+}
+
+pub fn read_ascii(text: &str) -> anyhow::Result<AhoyFrame> {
+    let mut frame = [0; DISPLAY_HEIGHT];
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.len() != DISPLAY_HEIGHT {
+        return Err(anyhow!(
+            "Expected {} rows of ASCII art, found {}",
+            DISPLAY_HEIGHT,
+            lines.len()
+        ));
+    }
+
+    for (row, line) in frame.iter_mut().zip(lines) {
+        if line.chars().count() != DISPLAY_WIDTH {
+            return Err(anyhow!("Expected {} columns in `{}`", DISPLAY_WIDTH, line));
+        }
+        for c in line.chars() {
+            *row = (*row << 1)
+                | match c {
+                    '#' => 1,
+                    '.' => 0,
+                    _ => return Err(anyhow!("Unexpected `{}` in ASCII art", c)),
+                };
+        }
+    }
+    Ok(frame)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Pbm,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Pbm => "pbm",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+// PBM is black and white by definition, so it ignores the palette
+pub fn write_image<W: Write>(
+    frame: &AhoyFrame,
+    format: ImageFormat,
+    scale: usize,
+    palette: &Palette,
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    match format {
+        ImageFormat::Pbm => write_pbm(frame, scale, writer),
+        ImageFormat::Png => write_png(frame, scale, palette, writer),
+    }
+}
+
+pub fn write_pbm<W: Write>(frame: &AhoyFrame, scale: usize, writer: &mut W) -> anyhow::Result<()> {
+    let scale = scale.max(1);
+    // Raw PBM rows are packed MSB first with 1 meaning black, which matches
+    // the frame layout once inverted
+    writeln!(
+        writer,
+        "P4\n{} {}",
+        DISPLAY_WIDTH * scale,
+        DISPLAY_HEIGHT * scale
+    )?;
+    for row in packed_rows(frame, scale) {
+        writer.write_all(&row.iter().map(|byte| !byte).collect::<Vec<_>>())?;
+    }
+    Ok(())
+}
+
+// A 1-bit indexed image, with the background and foreground as its palette
+pub fn write_png<W: Write>(
+    frame: &AhoyFrame,
+    scale: usize,
+    palette: &Palette,
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    let scale = scale.max(1);
+    let mut encoder = png::Encoder::new(
+        writer,
+        (DISPLAY_WIDTH * scale) as u32,
+        (DISPLAY_HEIGHT * scale) as u32,
+    );
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::One);
+    encoder.set_palette(indexed_colors(palette));
+
+    let data: Vec<u8> = packed_rows(frame, scale).into_iter().flatten().collect();
+    encoder.write_header()?.write_image_data(&data)?;
+    Ok(())
+}
+
+// Index 0 is the background and 1 a lit pixel, as packed by `packed_rows`
+pub(crate) fn indexed_colors(palette: &Palette) -> Vec<u8> {
+    [palette.background(), palette.foreground()]
+        .iter()
+        .flat_map(|Rgb(r, g, b)| [*r, *g, *b])
+        .collect()
+}
+
+// Scaled rows of lit pixels packed MSB first; widths are always whole bytes
+pub(crate) fn packed_rows(frame: &AhoyFrame, scale: usize) -> Vec<Vec<u8>> {
+    let width = DISPLAY_WIDTH * scale;
+    (0..DISPLAY_HEIGHT * scale)
+        .map(|y| {
+            let mut packed = vec![0_u8; width / 8];
+            for x in (0..width).filter(|x| pixel(frame, x / scale, y / scale)) {
+                packed[x / 8] |= 0x80 >> (x % 8);
+            }
+            packed
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        display::DISPLAY_HEIGHT,
+        export::{ImageFormat, read_ascii, write_ascii, write_image, write_pbm, write_png},
+        palette::Palette,
+    };
+
+    fn corners_frame() -> [u64; DISPLAY_HEIGHT] {
+        let mut frame = [0; DISPLAY_HEIGHT];
+        frame[0] = 0x8000000000000001;
+        frame[DISPLAY_HEIGHT - 1] = 0x8000000000000001;
+        frame
+    }
+
+    #[test]
+    fn ascii_draws_lit_pixels_as_hashes() {
+        let mut written = Vec::new();
+        write_ascii(&corners_frame(), &mut written).unwrap();
+        let text = String::from_utf8(written).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 32);
+        assert_eq!(lines[0], format!("#{}#", ".".repeat(62)));
+        assert_eq!(lines[1], ".".repeat(64));
+        assert_eq!(lines[31], lines[0]);
+    }
+
+    #[test]
+    fn ascii_reads_back_what_it_wrote() {
+        let mut written = Vec::new();
+        write_ascii(&corners_frame(), &mut written).unwrap();
+
+        let frame = read_ascii(&String::from_utf8(written).unwrap()).unwrap();
+
+        assert_eq!(frame, corners_frame());
+    }
+
+    #[test]
+    fn read_ascii_rejects_wrong_sizes() {
+        read_ascii("#.#\n").expect_err("Expected a tiny image to raise error");
+        read_ascii(&format!("{}\n", "x".repeat(64)).repeat(32))
+            .expect_err("Expected unknown pixels to raise error");
+    }
+
+    #[test]
+    fn pbm_has_header_and_inverted_packed_rows() {
+        let mut written = Vec::new();
+        write_pbm(&corners_frame(), 1, &mut written).unwrap();
+
+        assert!(written.starts_with(b"P4\n64 32\n"));
+        assert_eq!(written.len(), 9 + 32 * 8);
+        assert_eq!(
+            &written[9..17],
+            &[0x7F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE]
+        );
+        assert_eq!(&written[17..25], &[0xFF; 8]);
+    }
+
+    #[test]
+    fn png_decodes_back_to_the_frame() {
+        let mut written = Vec::new();
+        write_png(&corners_frame(), 1, &Palette::default(), &mut written).unwrap();
+
+        let mut reader = png::Decoder::new(std::io::Cursor::new(written))
+            .read_info()
+            .unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!((info.width, info.height), (64, 32));
+        assert_eq!(&pixels[0..8], &[0x80, 0, 0, 0, 0, 0, 0, 0x01]);
+        assert_eq!(&pixels[8..16], &[0; 8]);
+    }
+
+    #[test]
+    fn png_scales_every_pixel_into_a_square() {
+        let mut written = Vec::new();
+        write_png(&corners_frame(), 3, &Palette::default(), &mut written).unwrap();
+
+        let mut reader = png::Decoder::new(std::io::Cursor::new(written))
+            .read_info()
+            .unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!((info.width, info.height), (192, 96));
+        for row in pixels.chunks(info.line_size).take(3) {
+            assert_eq!(row[0], 0b1110_0000);
+            assert_eq!(row[23], 0b0000_0111);
+        }
+        assert_eq!(pixels[3 * info.line_size], 0);
+    }
+
+    #[test]
+    fn pbm_scales_rows_and_columns() {
+        let mut written = Vec::new();
+        write_pbm(&corners_frame(), 2, &mut written).unwrap();
+
+        assert!(written.starts_with(b"P4\n128 64\n"));
+        let rows: Vec<&[u8]> = written[10..].chunks(16).collect();
+        assert_eq!(rows.len(), 64);
+        assert_eq!(rows[0][0], 0x3F);
+        assert_eq!(rows[1], rows[0]);
+        assert_eq!(rows[2], &[0xFF; 16]);
+    }
+
+    #[test]
+    fn png_uses_the_palette_colours() {
+        let mut written = Vec::new();
+        let palette: Palette = "amber".parse().unwrap();
+        write_image(
+            &corners_frame(),
+            ImageFormat::Png,
+            1,
+            &palette,
+            &mut written,
+        )
+        .unwrap();
+
+        let reader = png::Decoder::new(std::io::Cursor::new(written))
+            .read_info()
+            .unwrap();
+
+        assert_eq!(
+            reader.info().palette.as_deref(),
+            Some(&[0x1A, 0x0F, 0x00, 0xFF, 0xB0, 0x00][..])
+        );
+    }
+}
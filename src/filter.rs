@@ -0,0 +1,214 @@
+use std::{collections::VecDeque, str::FromStr};
+
+use anyhow::anyhow;
+
+use crate::display::{AhoyFrame, DISPLAY_HEIGHT, DISPLAY_WIDTH, pixel};
+
+// Per-pixel brightness, 0 for the background up to 255 for a lit pixel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glow([[u8; DISPLAY_WIDTH]; DISPLAY_HEIGHT]);
+
+impl Glow {
+    pub fn from_frame(frame: &AhoyFrame) -> Self {
+        let mut glow = Self([[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT]);
+        for (y, row) in glow.0.iter_mut().enumerate() {
+            for (x, brightness) in row.iter_mut().enumerate() {
+                *brightness = if pixel(frame, x, y) { u8::MAX } else { 0 };
+            }
+        }
+        glow
+    }
+
+    pub fn at(&self, x: usize, y: usize) -> u8 {
+        self.0[y][x]
+    }
+
+    // Back to plain pixels for backends without partial brightness
+    pub fn threshold(&self) -> AhoyFrame {
+        let mut frame = [0; DISPLAY_HEIGHT];
+        for (row, glow) in frame.iter_mut().zip(&self.0) {
+            *row = glow
+                .iter()
+                .fold(0, |bits, brightness| bits << 1 | (*brightness > 0) as u64);
+        }
+        frame
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    Off,
+    // Keeps a pixel lit while it was lit in any of the last N frames
+    Blend(u8),
+    // Fades cleared pixels out over N frames like a CRT phosphor
+    Phosphor(u8),
+}
+
+impl FromStr for FilterMode {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (name, frames) = value.split_once(':').unwrap_or((value, "3"));
+        let frames: u8 = frames
+            .parse()
+            .map_err(|_| anyhow!("Expected a frame count in {:?}", value))?;
+        match name {
+            "off" => Ok(FilterMode::Off),
+            _ if frames == 0 => Err(anyhow!("The filter needs at least one frame")),
+            "blend" => Ok(FilterMode::Blend(frames)),
+            "phosphor" => Ok(FilterMode::Phosphor(frames)),
+            _ => Err(anyhow!(
+                "Unknown filter {:?}, expected off, blend[:N] or phosphor[:N]",
+                name
+            )),
+        }
+    }
+}
+
+pub struct FrameFilter {
+    mode: FilterMode,
+    history: VecDeque<AhoyFrame>,
+    glow: Glow,
+}
+
+impl FrameFilter {
+    pub fn new(mode: FilterMode) -> Self {
+        Self {
+            mode,
+            history: VecDeque::new(),
+            glow: Glow::from_frame(&[0; DISPLAY_HEIGHT]),
+        }
+    }
+
+    pub fn apply(&mut self, frame: &AhoyFrame) -> &Glow {
+        match self.mode {
+            FilterMode::Off => self.glow = Glow::from_frame(frame),
+            FilterMode::Blend(frames) => {
+                self.history.push_front(*frame);
+                self.history.truncate(frames as usize);
+                let mut blended = [0; DISPLAY_HEIGHT];
+                for past in &self.history {
+                    for (row, past_row) in blended.iter_mut().zip(past) {
+                        *row |= past_row;
+                    }
+                }
+                self.glow = Glow::from_frame(&blended);
+            }
+            FilterMode::Phosphor(frames) => {
+                let decay = (u8::MAX / (frames + 1)).max(1);
+                let lit = Glow::from_frame(frame);
+                for (row, lit_row) in self.glow.0.iter_mut().zip(&lit.0) {
+                    for (brightness, lit) in row.iter_mut().zip(lit_row) {
+                        *brightness = (*brightness).saturating_sub(decay).max(*lit);
+                    }
+                }
+            }
+        }
+        &self.glow
+    }
+
+    // Whether the output would keep changing even if the frame stays the same
+    pub fn is_fading(&self) -> bool {
+        match self.mode {
+            FilterMode::Off => false,
+            FilterMode::Blend(_) => self
+                .history
+                .iter()
+                .any(|past| Some(past) != self.history.front()),
+            FilterMode::Phosphor(_) => self
+                .glow
+                .0
+                .iter()
+                .flatten()
+                .any(|brightness| (1..u8::MAX).contains(brightness)),
+        }
+    }
+
+    // Drops the afterglow, e.g. after a state load or rewind
+    pub fn clear(&mut self) {
+        *self = Self::new(self.mode);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        display::DISPLAY_HEIGHT,
+        filter::{FilterMode, FrameFilter, Glow},
+    };
+
+    fn single_pixel() -> [u64; DISPLAY_HEIGHT] {
+        let mut frame = [0; DISPLAY_HEIGHT];
+        frame[2] = 1 << 60;
+        frame
+    }
+
+    #[test]
+    fn parses_filter_modes() {
+        assert_eq!("off".parse::<FilterMode>().unwrap(), FilterMode::Off);
+        assert_eq!("blend".parse::<FilterMode>().unwrap(), FilterMode::Blend(3));
+        assert_eq!(
+            "phosphor:5".parse::<FilterMode>().unwrap(),
+            FilterMode::Phosphor(5)
+        );
+        assert!("blend:0".parse::<FilterMode>().is_err());
+        assert!("blur".parse::<FilterMode>().is_err());
+    }
+
+    #[test]
+    fn glow_round_trips_through_threshold() {
+        let frame = single_pixel();
+        let glow = Glow::from_frame(&frame);
+
+        assert_eq!(glow.at(3, 2), 255);
+        assert_eq!(glow.at(4, 2), 0);
+        assert_eq!(glow.threshold(), frame);
+    }
+
+    #[test]
+    fn off_passes_frames_through() {
+        let mut filter = FrameFilter::new(FilterMode::Off);
+        filter.apply(&single_pixel());
+
+        assert_eq!(filter.apply(&[0; DISPLAY_HEIGHT]).at(3, 2), 0);
+    }
+
+    #[test]
+    fn blend_keeps_pixels_lit_for_n_frames() {
+        let mut filter = FrameFilter::new(FilterMode::Blend(2));
+        filter.apply(&single_pixel());
+
+        assert_eq!(filter.apply(&[0; DISPLAY_HEIGHT]).at(3, 2), 255);
+        assert_eq!(filter.apply(&[0; DISPLAY_HEIGHT]).at(3, 2), 0);
+    }
+
+    #[test]
+    fn phosphor_fades_cleared_pixels_out() {
+        let mut filter = FrameFilter::new(FilterMode::Phosphor(2));
+        filter.apply(&single_pixel());
+
+        assert_eq!(filter.apply(&[0; DISPLAY_HEIGHT]).at(3, 2), 170);
+        assert_eq!(filter.apply(&[0; DISPLAY_HEIGHT]).at(3, 2), 85);
+        assert_eq!(filter.apply(&[0; DISPLAY_HEIGHT]).at(3, 2), 0);
+        assert_eq!(filter.apply(&single_pixel()).at(3, 2), 255);
+    }
+
+    #[test]
+    fn filters_report_when_they_are_still_fading() {
+        let mut phosphor = FrameFilter::new(FilterMode::Phosphor(2));
+        phosphor.apply(&single_pixel());
+        assert!(!phosphor.is_fading());
+        phosphor.apply(&[0; DISPLAY_HEIGHT]);
+        phosphor.apply(&[0; DISPLAY_HEIGHT]);
+        assert!(phosphor.is_fading());
+        phosphor.apply(&[0; DISPLAY_HEIGHT]);
+        assert!(!phosphor.is_fading());
+
+        let mut blend = FrameFilter::new(FilterMode::Blend(2));
+        blend.apply(&single_pixel());
+        blend.apply(&[0; DISPLAY_HEIGHT]);
+        assert!(blend.is_fading());
+        blend.apply(&[0; DISPLAY_HEIGHT]);
+        assert!(!blend.is_fading());
+    }
+}
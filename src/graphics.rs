@@ -0,0 +1,338 @@
+use std::io::{Stdout, Write};
+
+use crossterm::{
+    cursor, execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use crate::{
+    display::{AhoyDisplay, AhoyFrame, DISPLAY_HEIGHT, DISPLAY_WIDTH, pixel},
+    export::write_png,
+    palette::{Palette, Rgb},
+};
+
+// Kitty rejects payload chunks longer than this
+const KITTY_CHUNK_SIZE: usize = 4096;
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsProtocol {
+    Sixel,
+    Kitty,
+}
+
+impl GraphicsProtocol {
+    // Best effort, as neither protocol has a reliable non-interactive query
+    pub fn detect() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        let term_program = var("TERM_PROGRAM");
+
+        if std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || term == "xterm-kitty"
+            || matches!(term_program.as_str(), "WezTerm" | "ghostty")
+        {
+            Some(GraphicsProtocol::Kitty)
+        } else if ["foot", "mlterm", "contour"]
+            .iter()
+            .any(|name| term.contains(name))
+        {
+            Some(GraphicsProtocol::Sixel)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn write_sixel<W: Write>(
+    frame: &AhoyFrame,
+    scale: usize,
+    palette: &Palette,
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    let scale = scale.max(1);
+    let (width, height) = (DISPLAY_WIDTH * scale, DISPLAY_HEIGHT * scale);
+    let lit = |x: usize, y: usize| y < height && pixel(frame, x / scale, y / scale);
+
+    write!(writer, "\x1bPq\"1;1;{width};{height}")?;
+    // Sixel colour registers take percentages rather than bytes
+    for (register, Rgb(r, g, b)) in [palette.background(), palette.foreground()]
+        .into_iter()
+        .enumerate()
+    {
+        let percent = |channel: u8| (channel as u32 * 100 + 127) / 255;
+        write!(
+            writer,
+            "#{register};2;{};{};{}",
+            percent(r),
+            percent(g),
+            percent(b)
+        )?;
+    }
+    for band in (0..height).step_by(6) {
+        for color in [0, 1] {
+            let sixels: Vec<u8> = (0..width)
+                .map(|x| {
+                    (0..6)
+                        .filter(|dy| band + dy < height && lit(x, band + dy) == (color == 1))
+                        .fold(0, |bits, dy| bits | (1 << dy))
+                })
+                .collect();
+            write!(writer, "#{color}")?;
+            for run in sixels.chunk_by(|a, b| a == b) {
+                let symbol = (63 + run[0]) as char;
+                match run.len() {
+                    1..=3 => write!(writer, "{}", symbol.to_string().repeat(run.len()))?,
+                    count => write!(writer, "!{count}{symbol}")?,
+                }
+            }
+            write!(writer, "$")?;
+        }
+        write!(writer, "-")?;
+    }
+    write!(writer, "\x1b\\")?;
+    Ok(())
+}
+
+pub fn write_kitty<W: Write>(
+    frame: &AhoyFrame,
+    scale: usize,
+    palette: &Palette,
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    let mut png = Vec::new();
+    write_png(frame, scale, palette, &mut png)?;
+    write_kitty_payload(base64(&png).as_bytes(), writer)
+}
+
+fn write_kitty_payload<W: Write>(payload: &[u8], writer: &mut W) -> anyhow::Result<()> {
+    let chunks: Vec<&[u8]> = payload.chunks(KITTY_CHUNK_SIZE).collect();
+    for (position, chunk) in chunks.iter().enumerate() {
+        let more = (position + 1 < chunks.len()) as u8;
+        // Reusing the image id replaces the previous frame in place
+        let control = if position == 0 {
+            format!("a=T,f=100,i=1,q=2,C=1,m={more}")
+        } else {
+            format!("m={more}")
+        };
+        writer.write_all(b"\x1b_G")?;
+        writer.write_all(control.as_bytes())?;
+        writer.write_all(b";")?;
+        writer.write_all(chunk)?;
+        writer.write_all(b"\x1b\\")?;
+    }
+    Ok(())
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let word = group.iter().enumerate().fold(0_u32, |word, (i, byte)| {
+            word | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= group.len() {
+                encoded.push(BASE64_ALPHABET[(word >> (18 - 6 * i)) as usize & 0x3F] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+pub struct GraphicsAhoyDisplay<W: Write> {
+    writer: W,
+    protocol: GraphicsProtocol,
+    scale: usize,
+    palette: Palette,
+    owns_terminal: bool,
+}
+
+impl<W: Write> GraphicsAhoyDisplay<W> {
+    pub fn new(writer: W, protocol: GraphicsProtocol, scale: usize, palette: Palette) -> Self {
+        Self {
+            writer,
+            protocol,
+            scale: scale.max(1),
+            palette,
+            owns_terminal: false,
+        }
+    }
+}
+
+impl GraphicsAhoyDisplay<Stdout> {
+    // Takes over the terminal and scales the image to the window's pixel size
+    pub fn terminal(protocol: GraphicsProtocol, palette: Palette) -> anyhow::Result<Self> {
+        let scale = terminal::window_size()
+            .ok()
+            .filter(|size| size.width > 0 && size.height > 0)
+            .map(|size| {
+                (size.width as usize / DISPLAY_WIDTH).min(size.height as usize / DISPLAY_HEIGHT)
+            })
+            .unwrap_or(1);
+
+        let mut stdout = std::io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(stdout, EnterAlternateScreen, cursor::Hide)?;
+        let mut display = Self::new(stdout, protocol, scale, palette);
+        display.owns_terminal = true;
+        Ok(display)
+    }
+}
+
+impl<W: Write> Drop for GraphicsAhoyDisplay<W> {
+    fn drop(&mut self) {
+        if self.owns_terminal {
+            let _ = execute!(self.writer, cursor::Show, LeaveAlternateScreen);
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+}
+
+impl<W: Write> AhoyDisplay for GraphicsAhoyDisplay<W> {
+    fn draw(&mut self, frame: &AhoyFrame) -> anyhow::Result<()> {
+        self.writer.write_all(b"\x1b[H")?;
+        match self.protocol {
+            GraphicsProtocol::Sixel => {
+                write_sixel(frame, self.scale, &self.palette, &mut self.writer)?
+            }
+            GraphicsProtocol::Kitty => {
+                write_kitty(frame, self.scale, &self.palette, &mut self.writer)?
+            }
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        display::{AhoyDisplay, DISPLAY_HEIGHT},
+        graphics::{
+            GraphicsAhoyDisplay, GraphicsProtocol, base64, write_kitty, write_kitty_payload,
+            write_sixel,
+        },
+        palette::Palette,
+    };
+
+    fn sixel(frame: &[u64; DISPLAY_HEIGHT]) -> String {
+        let mut written = Vec::new();
+        write_sixel(frame, 1, &Palette::default(), &mut written).unwrap();
+        String::from_utf8(written).unwrap()
+    }
+
+    #[test]
+    fn blank_frame_sixel_paints_every_band_black() {
+        let full_band = "#0!64~$#1!64?$-";
+        let last_band = "#0!64B$#1!64?$-";
+        let expected = format!(
+            "\x1bPq\"1;1;64;32#0;2;0;0;0#1;2;100;100;100{}{last_band}\x1b\\",
+            full_band.repeat(5)
+        );
+
+        assert_eq!(sixel(&[0; DISPLAY_HEIGHT]), expected);
+    }
+
+    #[test]
+    fn sixel_encodes_a_lit_pixel_in_its_band() {
+        let mut frame = [0; DISPLAY_HEIGHT];
+        frame[1] = 1 << 63;
+
+        let first_band = sixel(&frame)
+            .split('-')
+            .next()
+            .unwrap()
+            .rsplit("100;100;100")
+            .next()
+            .unwrap()
+            .to_owned();
+
+        assert_eq!(first_band, "#0|!63~$#1A!63?$");
+    }
+
+    #[test]
+    fn sixel_registers_take_the_palette_colours() {
+        let mut written = Vec::new();
+        let palette = "#FF8000,#000080".parse().unwrap();
+        write_sixel(&[0; DISPLAY_HEIGHT], 1, &palette, &mut written).unwrap();
+
+        assert!(
+            String::from_utf8(written)
+                .unwrap()
+                .starts_with("\x1bPq\"1;1;64;32#0;2;100;50;0#1;2;0;0;50#0")
+        );
+    }
+
+    #[test]
+    fn base64_matches_the_rfc_vectors() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn kitty_sends_the_frame_as_png() {
+        let mut frame = [0; DISPLAY_HEIGHT];
+        frame[0] = u64::MAX;
+        let mut written = Vec::new();
+        write_kitty(&frame, 8, &Palette::default(), &mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+
+        let payload = written
+            .strip_prefix("\x1b_Ga=T,f=100,i=1,q=2,C=1,m=0;")
+            .and_then(|rest| rest.strip_suffix("\x1b\\"))
+            .unwrap();
+        let png = decode_base64(payload);
+        let reader = png::Decoder::new(Cursor::new(png)).read_info().unwrap();
+        assert_eq!((reader.info().width, reader.info().height), (512, 256));
+    }
+
+    #[test]
+    fn kitty_splits_long_payloads_into_chunks() {
+        let mut written = Vec::new();
+        write_kitty_payload(&[b'A'; 5000], &mut written).unwrap();
+
+        let expected = format!(
+            "\x1b_Ga=T,f=100,i=1,q=2,C=1,m=1;{}\x1b\\\x1b_Gm=0;{}\x1b\\",
+            "A".repeat(4096),
+            "A".repeat(904)
+        );
+        assert_eq!(String::from_utf8(written).unwrap(), expected);
+    }
+
+    #[test]
+    fn display_homes_the_cursor_before_each_image() {
+        let mut written = Vec::new();
+        GraphicsAhoyDisplay::new(&mut written, GraphicsProtocol::Sixel, 1, Palette::default())
+            .draw(&[0; DISPLAY_HEIGHT])
+            .unwrap();
+
+        assert!(written.starts_with(b"\x1b[H\x1bPq"));
+    }
+
+    fn decode_base64(encoded: &str) -> Vec<u8> {
+        let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let sextets: Vec<u32> = encoded
+            .bytes()
+            .filter(|byte| *byte != b'=')
+            .map(|byte| alphabet.iter().position(|a| *a == byte).unwrap() as u32)
+            .collect();
+        sextets
+            .chunks(4)
+            .flat_map(|group| {
+                let word = group
+                    .iter()
+                    .enumerate()
+                    .fold(0, |word, (i, sextet)| word | sextet << (18 - 6 * i));
+                (0..group.len() - 1).map(move |i| (word >> (16 - 8 * i)) as u8)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    io::stdout,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags},
+    execute,
+    terminal::supports_keyboard_enhancement,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Hold {
+    #[default]
+    Released,
+    UntilRelease,
+    Until(Instant),
+}
+
+// A host key that stays down either until its release is reported or, on
+// terminals that only report presses, until a timeout after the last repeat
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeldKey(Hold);
+
+impl HeldKey {
+    // `auto_release` is None when the terminal will report the release
+    pub fn press(&mut self, now: Instant, auto_release: Option<Duration>) {
+        self.0 = match auto_release {
+            Some(timeout) => Hold::Until(now + timeout),
+            None => Hold::UntilRelease,
+        };
+    }
+
+    pub fn release(&mut self) {
+        self.0 = Hold::Released;
+    }
+
+    pub fn is_held(&self, now: Instant) -> bool {
+        match self.0 {
+            Hold::Released => false,
+            Hold::UntilRelease => true,
+            Hold::Until(deadline) => deadline > now,
+        }
+    }
+}
+
+// Asks the terminal for release events where it supports the kitty keyboard
+// protocol, undoing the request on drop
+pub struct KeyboardEnhancement;
+
+impl KeyboardEnhancement {
+    // Needs raw mode, as the support check reads the terminal's reply
+    pub fn enable() -> anyhow::Result<Option<Self>> {
+        if !supports_keyboard_enhancement()? {
+            return Ok(None);
+        }
+        execute!(
+            stdout(),
+            PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+            )
+        )?;
+        Ok(Some(Self))
+    }
+}
+
+impl Drop for KeyboardEnhancement {
+    fn drop(&mut self) {
+        let _ = execute!(stdout(), PopKeyboardEnhancementFlags);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::input::HeldKey;
+
+    #[test]
+    fn auto_released_keys_expire_after_the_timeout() {
+        let now = Instant::now();
+        let mut key = HeldKey::default();
+        assert!(!key.is_held(now));
+
+        key.press(now, Some(Duration::from_millis(150)));
+        assert!(key.is_held(now + Duration::from_millis(149)));
+        assert!(!key.is_held(now + Duration::from_millis(150)));
+    }
+
+    #[test]
+    fn reported_releases_hold_keys_until_released() {
+        let now = Instant::now();
+        let mut key = HeldKey::default();
+
+        key.press(now, None);
+        assert!(key.is_held(now + Duration::from_secs(60)));
+        key.release();
+        assert!(!key.is_held(now));
+    }
+}
@@ -0,0 +1,590 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    config::RomSettings,
+    constants::PROGRAM_MEMORY_START,
+    database::sha1_hex,
+    display::{DISPLAY_HEIGHT, DISPLAY_WIDTH, SPRITE_WIDTH},
+    instructions::AhoyInstruction,
+    quirks::{Platform, Quirks},
+};
+
+// The standard name of an opcode, e.g. 0x8AB4 -> "8XY4"
+pub fn opcode_pattern(opcode: u16) -> &'static str {
+    let (x, y, n) = ((opcode >> 8) & 0xF, (opcode >> 4) & 0xF, opcode & 0xF);
+    match (opcode >> 12, x, y, n) {
+        (0x0, 0x0, 0xC, _) => "00CN",
+        (0x0, 0x0, 0xD, _) => "00DN",
+        (0x0, 0x0, 0xE, 0x0) => "00E0",
+        (0x0, 0x0, 0xE, 0xE) => "00EE",
+        (0x0, 0x0, 0xF, 0xB) => "00FB",
+        (0x0, 0x0, 0xF, 0xC) => "00FC",
+        (0x0, 0x0, 0xF, 0xD) => "00FD",
+        (0x0, 0x0, 0xF, 0xE) => "00FE",
+        (0x0, 0x0, 0xF, 0xF) => "00FF",
+        (0x0, ..) => "0NNN",
+        (0x1, ..) => "1NNN",
+        (0x2, ..) => "2NNN",
+        (0x3, ..) => "3XNN",
+        (0x4, ..) => "4XNN",
+        (0x5, _, _, 0x0) => "5XY0",
+        (0x5, _, _, 0x2) => "5XY2",
+        (0x5, _, _, 0x3) => "5XY3",
+        (0x6, ..) => "6XNN",
+        (0x7, ..) => "7XNN",
+        (0x8, _, _, 0x0) => "8XY0",
+        (0x8, _, _, 0x1) => "8XY1",
+        (0x8, _, _, 0x2) => "8XY2",
+        (0x8, _, _, 0x3) => "8XY3",
+        (0x8, _, _, 0x4) => "8XY4",
+        (0x8, _, _, 0x5) => "8XY5",
+        (0x8, _, _, 0x6) => "8XY6",
+        (0x8, _, _, 0x7) => "8XY7",
+        (0x8, _, _, 0xE) => "8XYE",
+        (0x9, _, _, 0x0) => "9XY0",
+        (0xA, ..) => "ANNN",
+        (0xB, ..) => "BNNN",
+        (0xC, ..) => "CXNN",
+        (0xD, _, _, 0x0) => "DXY0",
+        (0xD, ..) => "DXYN",
+        (0xE, _, 0x9, 0xE) => "EX9E",
+        (0xE, _, 0xA, 0x1) => "EXA1",
+        (0xF, 0x0, 0x0, 0x0) => "F000",
+        (0xF, 0x0, 0x0, 0x2) => "F002",
+        (0xF, _, 0x0, 0x1) => "FX01",
+        (0xF, _, 0x0, 0x7) => "FX07",
+        (0xF, _, 0x0, 0xA) => "FX0A",
+        (0xF, _, 0x1, 0x5) => "FX15",
+        (0xF, _, 0x1, 0x8) => "FX18",
+        (0xF, _, 0x1, 0xE) => "FX1E",
+        (0xF, _, 0x2, 0x9) => "FX29",
+        (0xF, _, 0x3, 0x0) => "FX30",
+        (0xF, _, 0x3, 0x3) => "FX33",
+        (0xF, _, 0x3, 0xA) => "FX3A",
+        (0xF, _, 0x5, 0x5) => "FX55",
+        (0xF, _, 0x6, 0x5) => "FX65",
+        (0xF, _, 0x7, 0x5) => "FX75",
+        (0xF, _, 0x8, 0x5) => "FX85",
+        _ => "invalid",
+    }
+}
+
+const SUPER_CHIP_PATTERNS: [&str; 10] = [
+    "00CN", "00FB", "00FC", "00FD", "00FE", "00FF", "DXY0", "FX30", "FX75", "FX85",
+];
+const XO_CHIP_PATTERNS: [&str; 7] = ["00DN", "5XY2", "5XY3", "F000", "F002", "FX01", "FX3A"];
+
+// Instructions that leave straight-line code; calls come back, but with unknown registers
+const JUMPS: [&str; 5] = ["00EE", "00FD", "1NNN", "2NNN", "BNNN"];
+const SKIPS: [&str; 8] = [
+    "3XNN", "4XNN", "5XY0", "5XY2", "5XY3", "9XY0", "EX9E", "EXA1",
+];
+const READS_INDEX: [&str; 6] = ["DXYN", "DXY0", "FX1E", "FX33", "FX55", "FX65"];
+const SETS_INDEX: [&str; 4] = ["ANNN", "F000", "FX29", "FX30"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuirkPattern {
+    pub pattern: &'static str,
+    pub quirk: &'static str,
+    pub effect: &'static str,
+    pub addresses: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inspection {
+    pub size: usize,
+    pub sha1: String,
+    pub crc32: u32,
+    // Guessed from the opcodes alone
+    pub platform: Platform,
+    // Address and opcode of every instruction reachable from the entry point
+    pub reachable: BTreeMap<usize, u16>,
+    // Instruction family to the opcode patterns used from it and how often
+    pub families: BTreeMap<&'static str, BTreeMap<&'static str, usize>>,
+    // Opcode pattern `Ahoy` can't run yet to the addresses using it
+    pub unsupported: BTreeMap<&'static str, Vec<usize>>,
+    pub quirk_patterns: Vec<QuirkPattern>,
+    // BNNN targets depend on a register, so code behind them may be missed
+    pub indirect_jumps: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recommendation {
+    pub platform: Platform,
+    pub quirks: Quirks,
+    // Why a quirk differs from the platform preset
+    pub reasons: Vec<String>,
+    // Quirks the code depends on without showing which way, left at the preset
+    pub notes: Vec<String>,
+}
+
+impl Recommendation {
+    // The bottom settings layer for ROMs missing from the database
+    pub fn settings(&self) -> RomSettings {
+        RomSettings {
+            quirks: self.quirks.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Inspection {
+    fn sensitive(&self, quirk: &str) -> impl Iterator<Item = (usize, u16)> {
+        self.quirk_patterns
+            .iter()
+            .filter(move |found| found.quirk == quirk)
+            .flat_map(|found| &found.addresses)
+            .map(|addr| (*addr, self.reachable[addr]))
+    }
+
+    // Starts from the detected platform and only departs from it where the
+    // code gives a reason to
+    pub fn recommend(&self) -> Recommendation {
+        let mut quirks = Quirks::from(self.platform);
+        let mut reasons = Vec::new();
+
+        let shifts: Vec<_> = self.sensitive("shifting").collect();
+        if !shifts.is_empty() && shifts.iter().all(|(_, opcode)| (opcode >> 4) & 0xF == 0) {
+            quirks.shifting = true;
+            reasons.push("every shift names V0 as its source, so VX is shifted in place".into());
+        }
+
+        let next_uses: Vec<_> = self
+            .sensitive("memory-increment")
+            .filter_map(|(addr, _)| next_use_of_index(&self.reachable, addr))
+            .collect();
+        if !next_uses.is_empty() {
+            if next_uses.iter().all(|next| *next == "FX1E") {
+                quirks.memory_increment = false;
+                reasons.push("I is advanced by hand after storing or loading registers".into());
+            } else if next_uses
+                .iter()
+                .all(|next| matches!(*next, "FX55" | "FX65"))
+            {
+                quirks.memory_increment = true;
+                reasons
+                    .push("registers are stored or loaded in runs that rely on I advancing".into());
+            }
+        }
+
+        if self.sensitive("jumping").next().is_some() && !writes_v0(&self.reachable) {
+            quirks.jumping = true;
+            reasons.push("V0 is never set, so BXNN must add VX".into());
+        }
+
+        // Either way of resetting VF or clipping sprites is plausible code, so
+        // these findings are only reported
+        let mut notes = Vec::new();
+        for (quirk, enabled) in [("vf-reset", quirks.vf_reset), ("clipping", quirks.clipping)] {
+            if self.sensitive(quirk).next().is_some() {
+                let state = if enabled { "on" } else { "off" };
+                notes.push(format!(
+                    "{quirk} matters but the code doesn't tell which way, kept {state} as in {}",
+                    self.platform.name()
+                ));
+            }
+        }
+
+        Recommendation {
+            platform: self.platform,
+            quirks,
+            reasons,
+            notes,
+        }
+    }
+}
+
+pub fn inspect(rom: &[u8]) -> Inspection {
+    let reachable = reachable_code(rom);
+
+    let mut families: BTreeMap<_, BTreeMap<_, _>> = BTreeMap::new();
+    let mut unsupported: BTreeMap<&'static str, Vec<usize>> = BTreeMap::new();
+    let mut used = BTreeMap::<&'static str, Vec<usize>>::new();
+    for (addr, opcode) in &reachable {
+        let instruction = AhoyInstruction::from(*opcode);
+        let pattern = opcode_pattern(*opcode);
+        *families
+            .entry(instruction.family())
+            .or_default()
+            .entry(pattern)
+            .or_insert(0) += 1;
+        if let AhoyInstruction::UnknownInstruction(_) = instruction {
+            unsupported.entry(pattern).or_default().push(*addr);
+        }
+        used.entry(pattern).or_default().push(*addr);
+    }
+
+    let quirk_patterns = find_quirk_patterns(&reachable);
+    let platform = if XO_CHIP_PATTERNS
+        .iter()
+        .any(|pattern| used.contains_key(pattern))
+    {
+        Platform::XoChip
+    } else if SUPER_CHIP_PATTERNS
+        .iter()
+        .any(|pattern| used.contains_key(pattern))
+    {
+        Platform::SuperChip
+    } else {
+        Platform::Chip8
+    };
+
+    Inspection {
+        size: rom.len(),
+        sha1: sha1_hex(rom),
+        crc32: crc32(rom),
+        platform,
+        indirect_jumps: used.contains_key("BNNN"),
+        reachable,
+        families,
+        unsupported,
+        quirk_patterns,
+    }
+}
+
+// Follows jumps, calls and skips from the entry point so data mixed in with
+// the code isn't mistaken for instructions
+fn reachable_code(rom: &[u8]) -> BTreeMap<usize, u16> {
+    let end = PROGRAM_MEMORY_START + rom.len();
+    let opcode_at = |addr: usize| {
+        let offset = addr.checked_sub(PROGRAM_MEMORY_START)?;
+        Some(u16::from_be_bytes([
+            *rom.get(offset)?,
+            *rom.get(offset + 1)?,
+        ]))
+    };
+
+    let mut reachable = BTreeMap::new();
+    let mut pending = vec![PROGRAM_MEMORY_START];
+    let mut seen = BTreeSet::new();
+    while let Some(addr) = pending.pop() {
+        if addr >= end || !seen.insert(addr) {
+            continue;
+        }
+        let Some(opcode) = opcode_at(addr) else {
+            continue;
+        };
+        reachable.insert(addr, opcode);
+
+        let target = (opcode & 0x0FFF) as usize;
+        // XO-CHIP's F000 NNNN is twice as long as every other instruction
+        let next = match opcode_at(addr + 2) {
+            Some(_) if opcode_pattern(opcode) == "F000" => addr + 4,
+            _ => addr + 2,
+        };
+        match opcode_pattern(opcode) {
+            "00EE" | "00FD" | "BNNN" => {}
+            "1NNN" => pending.push(target),
+            "2NNN" => pending.extend([target, next]),
+            pattern if SKIPS.contains(&pattern) => pending.extend([next, next + 2]),
+            _ => pending.push(next),
+        }
+    }
+    reachable
+}
+
+// Whether the ROM ever changes V0, which BNNN reads without the jumping quirk
+fn writes_v0(reachable: &BTreeMap<usize, u16>) -> bool {
+    reachable.values().any(|opcode| {
+        let x = (opcode >> 8) & 0xF;
+        match opcode_pattern(*opcode) {
+            "FX65" | "FX85" => true,
+            "6XNN" | "7XNN" | "CXNN" | "FX07" | "FX0A" => x == 0,
+            pattern => pattern.starts_with("8XY") && x == 0,
+        }
+    })
+}
+
+// The first instruction after `addr` in straight-line code that reads or
+// replaces I
+fn next_use_of_index(reachable: &BTreeMap<usize, u16>, addr: usize) -> Option<&'static str> {
+    let mut addr = addr + 2;
+    while let Some(opcode) = reachable.get(&addr) {
+        let pattern = opcode_pattern(*opcode);
+        if READS_INDEX.contains(&pattern) || SETS_INDEX.contains(&pattern) {
+            return Some(pattern);
+        }
+        if JUMPS.contains(&pattern) || SKIPS.contains(&pattern) {
+            return None;
+        }
+        addr += 2;
+    }
+    None
+}
+
+// Registers whose value is known from earlier 6XNN, 7XNN and 8XY0 in the same
+// straight-line code
+fn track_registers(registers: &mut [Option<u8>; 16], opcode: u16) {
+    let (x, y) = (
+        ((opcode >> 8) & 0xF) as usize,
+        ((opcode >> 4) & 0xF) as usize,
+    );
+    let value = (opcode & 0xFF) as u8;
+    match opcode_pattern(opcode) {
+        "6XNN" => registers[x] = Some(value),
+        "7XNN" => registers[x] = registers[x].map(|register| register.wrapping_add(value)),
+        "8XY0" => registers[x] = registers[y],
+        "CXNN" | "FX07" | "FX0A" => registers[x] = None,
+        "DXYN" | "DXY0" => registers[0xF] = None,
+        "FX65" | "FX85" => registers[..=x].fill(None),
+        pattern if pattern.starts_with("8XY") => {
+            registers[x] = None;
+            registers[0xF] = None;
+        }
+        _ => {}
+    }
+}
+
+// Where a sprite drawn from known coordinates runs off the screen
+fn crosses_edge(registers: &[Option<u8>; 16], opcode: u16) -> bool {
+    let (x, y) = (
+        ((opcode >> 8) & 0xF) as usize,
+        ((opcode >> 4) & 0xF) as usize,
+    );
+    let (Some(left), Some(top)) = (registers[x], registers[y]) else {
+        return false;
+    };
+    let (width, height) = match opcode & 0xF {
+        0 => (16, 16),
+        rows => (SPRITE_WIDTH, rows as usize),
+    };
+    left as usize % DISPLAY_WIDTH + width > DISPLAY_WIDTH
+        || top as usize % DISPLAY_HEIGHT + height > DISPLAY_HEIGHT
+}
+
+// Instructions that behave differently depending on a quirk, grouped by
+// opcode pattern and quirk
+fn find_quirk_patterns(reachable: &BTreeMap<usize, u16>) -> Vec<QuirkPattern> {
+    // Jump targets and the ends of calls and skips can be reached with
+    // different register values
+    let mut merges = BTreeSet::new();
+    for (addr, opcode) in reachable {
+        match opcode_pattern(*opcode) {
+            "1NNN" => {
+                merges.insert((opcode & 0x0FFF) as usize);
+            }
+            "2NNN" => merges.extend([(opcode & 0x0FFF) as usize, addr + 2]),
+            pattern if SKIPS.contains(&pattern) => {
+                merges.insert(addr + 4);
+            }
+            _ => {}
+        }
+    }
+
+    let mut found: Vec<QuirkPattern> = Vec::new();
+    let mut registers = [None; 16];
+    let mut fallthrough = None;
+    for (&addr, &opcode) in reachable {
+        if fallthrough != Some(addr) || merges.contains(&addr) {
+            registers = [None; 16];
+        }
+        let (x, y) = ((opcode >> 8) & 0xF, (opcode >> 4) & 0xF);
+        let pattern = opcode_pattern(opcode);
+        let sensitivity = match pattern {
+            "8XY1" => Some(("vf-reset", "OR may reset VF")),
+            "8XY2" => Some(("vf-reset", "AND may reset VF")),
+            "8XY3" => Some(("vf-reset", "XOR may reset VF")),
+            "8XY6" if x != y => Some(("shifting", "right shift may read VX instead of VY")),
+            "8XYE" if x != y => Some(("shifting", "left shift may read VX instead of VY")),
+            "FX55" | "FX65"
+                if next_use_of_index(reachable, addr)
+                    .is_some_and(|next| READS_INDEX.contains(&next)) =>
+            {
+                Some(("memory-increment", "I is read again afterwards"))
+            }
+            "BNNN" if x != 0 => Some(("jumping", "offset may come from VX instead of V0")),
+            "DXYN" | "DXY0" if crosses_edge(&registers, opcode) => {
+                Some(("clipping", "sprite at a fixed position crosses the edge"))
+            }
+            _ => None,
+        };
+        if let Some((quirk, effect)) = sensitivity {
+            match found
+                .iter_mut()
+                .find(|found| found.pattern == pattern && found.quirk == quirk)
+            {
+                Some(found) => found.addresses.push(addr),
+                None => found.push(QuirkPattern {
+                    pattern,
+                    quirk,
+                    effect,
+                    addresses: vec![addr],
+                }),
+            }
+        }
+        track_registers(&mut registers, opcode);
+        fallthrough = (!JUMPS.contains(&pattern)).then_some(addr + 2);
+    }
+    found
+}
+
+// CRC-32 as used by zip and most ROM catalogues
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| {
+            (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        inspect::{crc32, inspect, opcode_pattern},
+        quirks::{Platform, Quirks},
+    };
+
+    #[test]
+    fn names_opcodes_by_pattern() {
+        assert_eq!(opcode_pattern(0x00E0), "00E0");
+        assert_eq!(opcode_pattern(0x8AB4), "8XY4");
+        assert_eq!(opcode_pattern(0xD120), "DXY0");
+        assert_eq!(opcode_pattern(0xF265), "FX65");
+        assert_eq!(opcode_pattern(0x5121), "invalid");
+    }
+
+    #[test]
+    fn crc32_matches_the_reference_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn skips_data_that_is_never_executed() {
+        // Jump over two bytes of sprite data, then loop forever
+        let rom = [0x12, 0x04, 0xFF, 0xFF, 0x60, 0x01, 0x12, 0x06];
+        let inspection = inspect(&rom);
+
+        assert_eq!(
+            inspection.reachable.keys().copied().collect::<Vec<_>>(),
+            vec![0x200, 0x204, 0x206]
+        );
+        assert_eq!(inspection.families["jump"]["1NNN"], 2);
+        assert_eq!(inspection.families["set register"]["6XNN"], 1);
+    }
+
+    #[test]
+    fn follows_calls_and_both_sides_of_skips() {
+        let rom = [
+            0x22, 0x08, // call 0x208
+            0x30, 0x01, // skip if V0 == 1
+            0x12, 0x06, // jump 0x206
+            0x12, 0x06, // loop
+            0x81, 0x26, // shift, then return
+            0x00, 0xEE,
+        ];
+        let inspection = inspect(&rom);
+
+        assert_eq!(
+            inspection.reachable.keys().copied().collect::<Vec<_>>(),
+            vec![0x200, 0x202, 0x204, 0x206, 0x208, 0x20A]
+        );
+        assert_eq!(inspection.quirk_patterns[0].quirk, "shifting");
+        assert_eq!(inspection.quirk_patterns[0].addresses, vec![0x208]);
+    }
+
+    #[test]
+    fn groups_key_skips_with_their_patterns() {
+        let rom = [
+            0xE1, 0x9E, // skip if key V1 is down
+            0xE1, 0xA1, // skip if key V1 is up
+            0x12, 0x00,
+        ];
+        let inspection = inspect(&rom);
+
+        assert_eq!(inspection.families["skip on key"]["EX9E"], 1);
+        assert_eq!(inspection.families["skip on key"]["EXA1"], 1);
+        assert!(inspection.unsupported.is_empty());
+    }
+
+    #[test]
+    fn lists_opcodes_ahoy_cannot_run() {
+        // 5XY1 is not an instruction on any platform
+        let rom = [0x51, 0x21, 0x12, 0x00];
+        let inspection = inspect(&rom);
+
+        assert_eq!(inspection.unsupported["invalid"], vec![0x200]);
+        assert_eq!(inspection.families["unknown"]["invalid"], 1);
+    }
+
+    #[test]
+    fn guesses_the_platform_from_extended_opcodes() {
+        assert_eq!(inspect(&[0x00, 0xE0, 0x12, 0x00]).platform, Platform::Chip8);
+        assert_eq!(
+            inspect(&[0x00, 0xFF, 0x12, 0x00]).platform,
+            Platform::SuperChip
+        );
+        assert_eq!(
+            inspect(&[0xF0, 0x00, 0x12, 0x34, 0x12, 0x04]).platform,
+            Platform::XoChip
+        );
+    }
+
+    #[test]
+    fn shifts_only_depend_on_the_quirk_when_x_and_y_differ() {
+        let rom = [0x81, 0x16, 0x81, 0x0E, 0x12, 0x04];
+        let inspection = inspect(&rom);
+
+        assert_eq!(inspection.quirk_patterns.len(), 1);
+        assert_eq!(inspection.quirk_patterns[0].pattern, "8XYE");
+        let recommendation = inspection.recommend();
+        assert!(recommendation.quirks.shifting);
+        assert_eq!(recommendation.reasons.len(), 1);
+    }
+
+    #[test]
+    fn register_loads_matter_when_i_is_read_before_being_reset() {
+        // Load, reload I, draw: the increment is never observed
+        let reloaded = [0xF2, 0x65, 0xA3, 0x00, 0xD0, 0x15, 0x12, 0x06];
+        assert!(inspect(&reloaded).quirk_patterns.is_empty());
+
+        // Load, then step past the registers by hand
+        let by_hand = [0xF2, 0x65, 0x63, 0x03, 0xF3, 0x1E, 0x12, 0x06];
+        let inspection = inspect(&by_hand);
+        assert_eq!(inspection.quirk_patterns[0].quirk, "memory-increment");
+        assert!(!inspection.recommend().quirks.memory_increment);
+    }
+
+    #[test]
+    fn jumps_read_vx_when_v0_is_never_set() {
+        let rom = [0x61, 0x02, 0xB2, 0x04, 0x12, 0x04];
+        assert!(inspect(&rom).recommend().quirks.jumping);
+
+        let sets_v0 = [0x60, 0x02, 0xB2, 0x04, 0x12, 0x04];
+        assert_eq!(
+            inspect(&sets_v0).recommend().quirks,
+            Quirks::from(Platform::Chip8)
+        );
+    }
+
+    #[test]
+    fn flags_sprites_drawn_across_the_edge_from_known_coordinates() {
+        let rom = [
+            0x60, 0x3C, // V0 = 60
+            0x61, 0x00, // V1 = 0
+            0xD0, 0x15, // draw at (60, 0)
+            0x70, 0x01, // V0 += 1, unknown once the loop comes back here
+            0xD0, 0x15, 0x12, 0x06,
+        ];
+        let inspection = inspect(&rom);
+
+        assert_eq!(inspection.quirk_patterns[0].quirk, "clipping");
+        assert_eq!(inspection.quirk_patterns[0].addresses, vec![0x204]);
+    }
+
+    #[test]
+    fn vf_reset_and_clipping_findings_leave_the_preset_alone() {
+        let rom = [
+            0x60, 0x3C, // V0 = 60
+            0x62, 0x00, // V2 = 0
+            0x81, 0x21, // V1 |= V2
+            0xD0, 0x25, // draw at (60, 0)
+            0x12, 0x08,
+        ];
+        let recommendation = inspect(&rom).recommend();
+
+        assert_eq!(recommendation.quirks, Quirks::from(Platform::Chip8));
+        assert!(recommendation.reasons.is_empty());
+        assert_eq!(recommendation.notes.len(), 2);
+        assert!(recommendation.notes[0].starts_with("vf-reset"));
+    }
+}
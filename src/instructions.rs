@@ -3,9 +3,36 @@
 pub enum AhoyInstruction {
     Jump(usize),
     CallSubroutine(u16),
+    SkipIfEqual(usize, u8),
+    SkipIfNotEqual(usize, u8),
+    SkipIfRegistersEqual(usize, usize),
+    SkipIfRegistersNotEqual(usize, usize),
+    SkipIfKeyPressed(usize),
+    SkipIfKeyNotPressed(usize),
     SetRegister(usize, u8),
     AddToRegister(usize, u8),
+    CopyRegister(usize, usize),
+    Or(usize, usize),
+    And(usize, usize),
+    Xor(usize, usize),
+    AddRegisters(usize, usize),
+    // VX - VY and VY - VX
+    Subtract(usize, usize),
+    SubtractReversed(usize, usize),
+    ShiftRight(usize, usize),
+    ShiftLeft(usize, usize),
     SetIndex(u16),
+    JumpWithOffset(u16),
+    Random(usize, u8),
+    ReadDelayTimer(usize),
+    SetDelayTimer(usize),
+    SetSoundTimer(usize),
+    AddToIndex(usize),
+    FontCharacter(usize),
+    StoreDecimal(usize),
+    StoreRegisters(usize),
+    LoadRegisters(usize),
+    WaitForKey(usize),
     Display {
         x_register: usize,
         y_register: usize,
@@ -16,6 +43,45 @@ pub enum AhoyInstruction {
     UnknownInstruction(u16),
 }
 
+impl AhoyInstruction {
+    pub fn family(&self) -> &'static str {
+        match self {
+            AhoyInstruction::Jump(_) | AhoyInstruction::JumpWithOffset(_) => "jump",
+            AhoyInstruction::CallSubroutine(_) => "call",
+            AhoyInstruction::SkipIfEqual(..)
+            | AhoyInstruction::SkipIfNotEqual(..)
+            | AhoyInstruction::SkipIfRegistersEqual(..)
+            | AhoyInstruction::SkipIfRegistersNotEqual(..) => "skip",
+            AhoyInstruction::SkipIfKeyPressed(_) | AhoyInstruction::SkipIfKeyNotPressed(_) => {
+                "skip on key"
+            }
+            AhoyInstruction::SetRegister(..) | AhoyInstruction::CopyRegister(..) => "set register",
+            AhoyInstruction::AddToRegister(..) => "add to register",
+            AhoyInstruction::Or(..) | AhoyInstruction::And(..) | AhoyInstruction::Xor(..) => {
+                "logic"
+            }
+            AhoyInstruction::AddRegisters(..)
+            | AhoyInstruction::Subtract(..)
+            | AhoyInstruction::SubtractReversed(..) => "arithmetic",
+            AhoyInstruction::ShiftRight(..) | AhoyInstruction::ShiftLeft(..) => "shift",
+            AhoyInstruction::SetIndex(_)
+            | AhoyInstruction::AddToIndex(_)
+            | AhoyInstruction::FontCharacter(_) => "set index",
+            AhoyInstruction::Random(..) => "random",
+            AhoyInstruction::ReadDelayTimer(_)
+            | AhoyInstruction::SetDelayTimer(_)
+            | AhoyInstruction::SetSoundTimer(_) => "timer",
+            AhoyInstruction::StoreDecimal(_) => "decimal",
+            AhoyInstruction::StoreRegisters(_) | AhoyInstruction::LoadRegisters(_) => "memory",
+            AhoyInstruction::Display { .. } => "display",
+            AhoyInstruction::WaitForKey(_) => "wait for key",
+            AhoyInstruction::ClearScreen => "clear screen",
+            AhoyInstruction::StopSubroutine => "return",
+            AhoyInstruction::UnknownInstruction(_) => "unknown",
+        }
+    }
+}
+
 trait RegisterInstruction {
     fn into_regsiter_instruction(self) -> (u8, u8);
 }
@@ -26,6 +92,14 @@ impl RegisterInstruction for u16 {
     }
 }
 
+// The X and Y register nibbles of an opcode
+fn registers(instruction: u16) -> (usize, usize) {
+    (
+        ((instruction >> 8) & 0xF) as usize,
+        ((instruction >> 4) & 0xF) as usize,
+    )
+}
+
 impl From<u16> for AhoyInstruction {
     fn from(value: u16) -> Self {
         match value {
@@ -34,6 +108,18 @@ impl From<u16> for AhoyInstruction {
             instruction => match instruction >> 0xC {
                 1 => Self::Jump((instruction & 0x0FFF) as usize),
                 2 => Self::CallSubroutine(instruction & 0x0FFF),
+                3 => {
+                    let (addr, value) = instruction.into_regsiter_instruction();
+                    Self::SkipIfEqual(addr as usize, value)
+                }
+                4 => {
+                    let (addr, value) = instruction.into_regsiter_instruction();
+                    Self::SkipIfNotEqual(addr as usize, value)
+                }
+                5 if instruction & 0xF == 0 => {
+                    let (x, y) = registers(instruction);
+                    Self::SkipIfRegistersEqual(x, y)
+                }
                 6 => {
                     let (addr, value) = instruction.into_regsiter_instruction();
                     Self::SetRegister(addr as usize, value)
@@ -42,12 +128,59 @@ impl From<u16> for AhoyInstruction {
                     let (addr, value) = instruction.into_regsiter_instruction();
                     Self::AddToRegister(addr as usize, value)
                 }
+                8 => {
+                    let (x, y) = registers(instruction);
+                    match instruction & 0xF {
+                        0x0 => Self::CopyRegister(x, y),
+                        0x1 => Self::Or(x, y),
+                        0x2 => Self::And(x, y),
+                        0x3 => Self::Xor(x, y),
+                        0x4 => Self::AddRegisters(x, y),
+                        0x5 => Self::Subtract(x, y),
+                        0x6 => Self::ShiftRight(x, y),
+                        0x7 => Self::SubtractReversed(x, y),
+                        0xE => Self::ShiftLeft(x, y),
+                        _ => Self::UnknownInstruction(instruction),
+                    }
+                }
+                9 if instruction & 0xF == 0 => {
+                    let (x, y) = registers(instruction);
+                    Self::SkipIfRegistersNotEqual(x, y)
+                }
                 0xA => Self::SetIndex(instruction & 0x0FFF),
+                0xB => Self::JumpWithOffset(instruction & 0x0FFF),
+                0xC => {
+                    let (addr, mask) = instruction.into_regsiter_instruction();
+                    Self::Random(addr as usize, mask)
+                }
                 0xD => Self::Display {
                     x_register: ((instruction >> 8) & 0xF) as usize,
                     y_register: ((instruction >> 4) & 0xF) as usize,
                     sprite_height: (instruction & 0xF) as u8,
                 },
+                0xE => {
+                    let (x, _) = registers(instruction);
+                    match instruction & 0xFF {
+                        0x9E => Self::SkipIfKeyPressed(x),
+                        0xA1 => Self::SkipIfKeyNotPressed(x),
+                        _ => Self::UnknownInstruction(instruction),
+                    }
+                }
+                0xF => {
+                    let (x, _) = registers(instruction);
+                    match instruction & 0xFF {
+                        0x07 => Self::ReadDelayTimer(x),
+                        0x0A => Self::WaitForKey(x),
+                        0x15 => Self::SetDelayTimer(x),
+                        0x18 => Self::SetSoundTimer(x),
+                        0x1E => Self::AddToIndex(x),
+                        0x29 => Self::FontCharacter(x),
+                        0x33 => Self::StoreDecimal(x),
+                        0x55 => Self::StoreRegisters(x),
+                        0x65 => Self::LoadRegisters(x),
+                        _ => Self::UnknownInstruction(instruction),
+                    }
+                }
                 _ => Self::UnknownInstruction(instruction),
             },
         }
@@ -126,6 +259,97 @@ mod tests {
         assert!(matches!(0xA000.into(), AhoyInstruction::SetIndex(0x000)));
     }
 
+    #[test]
+    fn decode_random_instruction() {
+        assert!(matches!(0xC0FF.into(), AhoyInstruction::Random(0x0, 0xFF)));
+        assert!(matches!(0xCA0F.into(), AhoyInstruction::Random(0xA, 0x0F)));
+    }
+
+    #[test]
+    fn decode_wait_for_key_instruction() {
+        assert!(matches!(0xF30A.into(), AhoyInstruction::WaitForKey(0x3)));
+        assert!(matches!(
+            0xF30B.into(),
+            AhoyInstruction::UnknownInstruction(0xF30B)
+        ));
+    }
+
+    #[test]
+    fn decode_skip_instructions() {
+        assert!(matches!(
+            0x3A42.into(),
+            AhoyInstruction::SkipIfEqual(0xA, 0x42)
+        ));
+        assert!(matches!(
+            0x4A42.into(),
+            AhoyInstruction::SkipIfNotEqual(0xA, 0x42)
+        ));
+        assert!(matches!(
+            0x5AB0.into(),
+            AhoyInstruction::SkipIfRegistersEqual(0xA, 0xB)
+        ));
+        assert!(matches!(
+            0x9AB0.into(),
+            AhoyInstruction::SkipIfRegistersNotEqual(0xA, 0xB)
+        ));
+        assert!(matches!(
+            0x5AB1.into(),
+            AhoyInstruction::UnknownInstruction(0x5AB1)
+        ));
+    }
+
+    #[test]
+    fn decode_register_arithmetic_instructions() {
+        assert!(matches!(0x8120.into(), AhoyInstruction::CopyRegister(1, 2)));
+        assert!(matches!(0x8121.into(), AhoyInstruction::Or(1, 2)));
+        assert!(matches!(0x8122.into(), AhoyInstruction::And(1, 2)));
+        assert!(matches!(0x8123.into(), AhoyInstruction::Xor(1, 2)));
+        assert!(matches!(0x8124.into(), AhoyInstruction::AddRegisters(1, 2)));
+        assert!(matches!(0x8125.into(), AhoyInstruction::Subtract(1, 2)));
+        assert!(matches!(0x8126.into(), AhoyInstruction::ShiftRight(1, 2)));
+        assert!(matches!(
+            0x8127.into(),
+            AhoyInstruction::SubtractReversed(1, 2)
+        ));
+        assert!(matches!(0x812E.into(), AhoyInstruction::ShiftLeft(1, 2)));
+        assert!(matches!(
+            0x8128.into(),
+            AhoyInstruction::UnknownInstruction(0x8128)
+        ));
+    }
+
+    #[test]
+    fn decode_key_skip_instructions() {
+        assert!(matches!(
+            0xE39E.into(),
+            AhoyInstruction::SkipIfKeyPressed(0x3)
+        ));
+        assert!(matches!(
+            0xEBA1.into(),
+            AhoyInstruction::SkipIfKeyNotPressed(0xB)
+        ));
+        assert!(matches!(
+            0xE3A2.into(),
+            AhoyInstruction::UnknownInstruction(0xE3A2)
+        ));
+    }
+
+    #[test]
+    fn decode_f_instructions() {
+        assert!(matches!(
+            0xB123.into(),
+            AhoyInstruction::JumpWithOffset(0x123)
+        ));
+        assert!(matches!(0xF307.into(), AhoyInstruction::ReadDelayTimer(3)));
+        assert!(matches!(0xF315.into(), AhoyInstruction::SetDelayTimer(3)));
+        assert!(matches!(0xF318.into(), AhoyInstruction::SetSoundTimer(3)));
+        assert!(matches!(0xF31E.into(), AhoyInstruction::AddToIndex(3)));
+        assert!(matches!(0xF329.into(), AhoyInstruction::FontCharacter(3)));
+        assert!(matches!(0xF333.into(), AhoyInstruction::StoreDecimal(3)));
+        assert!(matches!(0xF355.into(), AhoyInstruction::StoreRegisters(3)));
+        assert!(matches!(0xF365.into(), AhoyInstruction::LoadRegisters(3)));
+    }
+
     #[test]
     fn decode_display_instruction() {
         assert!(matches!(
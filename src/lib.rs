@@ -1,14 +1,38 @@
+pub mod animation;
+pub mod cast;
+pub mod config;
+pub mod conformance;
 mod constants;
-mod display;
-mod instructions;
+pub mod controls;
+pub mod database;
+pub mod debugger;
+pub mod display;
+pub mod export;
+pub mod filter;
+pub mod graphics;
+pub mod input;
+pub mod inspect;
+pub mod instructions;
+pub mod movie;
+pub mod palette;
+pub mod quirks;
+pub mod rewind;
+pub mod sanitizer;
+pub mod state;
 
 use anyhow::anyhow;
 use cli_log::debug;
-use constants::{FLAG_REGISTER, MAX_MEMORY, PROGRAM_MEMORY_START};
+use constants::{
+    FLAG_REGISTER, FONT_CHARACTER_HEIGHT, FONT_START, MAX_MEMORY, MAX_STACK_DEPTH,
+    PROGRAM_MEMORY_START,
+};
 use display::{AhoyFrame, DISPLAY_HEIGHT, DISPLAY_WIDTH, SPRITE_WIDTH};
 use instructions::AhoyInstruction;
+use quirks::Quirks;
+use sanitizer::{Sanitizer, SanitizerReport};
 use std::{collections::VecDeque, io::BufRead};
 
+#[derive(Clone)]
 pub struct Ahoy {
     memory: [u8; constants::MAX_MEMORY],
     registers: [u8; 16],
@@ -18,13 +42,22 @@ pub struct Ahoy {
     delay_timer: u8,
     sound_timer: u8,
     pub current_frame: AhoyFrame,
+    frame_generation: u64,
+    keypad: u16,
+    // Key pressed during FX0A, which only completes once it is released
+    awaited_key: Option<u8>,
+    rng_state: u64,
+    pub quirks: Quirks,
+    sanitizer: Option<Sanitizer>,
 }
 
+const DEFAULT_RNG_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
 impl Default for Ahoy {
     fn default() -> Self {
         let mut memory = [0; constants::MAX_MEMORY];
 
-        memory[0x050..=0x09F].copy_from_slice(&constants::FONT);
+        memory[FONT_START..FONT_START + constants::FONT.len()].copy_from_slice(&constants::FONT);
 
         Ahoy {
             memory,
@@ -35,6 +68,12 @@ impl Default for Ahoy {
             delay_timer: 0,
             sound_timer: 0,
             current_frame: [0; DISPLAY_HEIGHT],
+            frame_generation: 0,
+            keypad: 0,
+            awaited_key: None,
+            rng_state: DEFAULT_RNG_SEED,
+            quirks: Quirks::default(),
+            sanitizer: None,
         }
     }
 }
@@ -60,15 +99,103 @@ impl Ahoy {
             return Err(anyhow!("Program exceeds memory limits"));
         }
 
+        let loaded = PROGRAM_MEMORY_START..PROGRAM_MEMORY_START + total_bytes_read;
+        if let Some(sanitizer) = &mut self.sanitizer {
+            sanitizer.mark_loaded(loaded);
+        }
+
+        Ok(())
+    }
+
+    // Needs to happen before `load` so the sanitizer knows which bytes are the ROM
+    pub fn enable_sanitizer(&mut self) {
+        self.sanitizer = Some(Sanitizer::default());
+    }
+
+    pub fn sanitizer_reports(&self) -> &[SanitizerReport] {
+        self.sanitizer.as_ref().map_or(&[], Sanitizer::reports)
+    }
+
+    pub fn seed_rng(&mut self, seed: u64) {
+        // xorshift gets stuck on a zero state
+        self.rng_state = if seed == 0 { DEFAULT_RNG_SEED } else { seed };
+    }
+
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        let mask = 1 << (key & 0xF);
+        if pressed {
+            self.keypad |= mask;
+        } else {
+            self.keypad &= !mask;
+        }
+    }
+
+    pub fn keypad(&self) -> u16 {
+        self.keypad
+    }
+
+    // The register FX0A will store a key in, while the program is stuck on it
+    pub fn awaiting_key(&self) -> Option<usize> {
+        match self.current_instruction() {
+            AhoyInstruction::WaitForKey(register_addr) => Some(register_addr),
+            _ => None,
+        }
+    }
+
+    // Bumped whenever an instruction changes the frame, so callers can skip
+    // redrawing identical frames
+    pub fn frame_generation(&self) -> u64 {
+        self.frame_generation
+    }
+
+    pub fn run_frame(&mut self, instructions_per_frame: usize) -> anyhow::Result<()> {
+        for _ in 0..instructions_per_frame {
+            // The COSMAC VIP waits for the display interrupt, so a sprite ends the frame
+            let draws = matches!(self.current_instruction(), AhoyInstruction::Display { .. });
+            self.process()?;
+            if draws && self.quirks.display_wait {
+                break;
+            }
+        }
+        self.tick_timers();
         Ok(())
     }
 
+    fn tick_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    // Programs conventionally end by jumping to themselves forever
+    pub fn is_idle(&self) -> bool {
+        matches!(self.current_instruction(), AhoyInstruction::Jump(addr) if addr == self.counter)
+    }
+
+    fn current_instruction(&self) -> AhoyInstruction {
+        AhoyInstruction::from(u16::from_be_bytes([
+            self.memory[self.counter],
+            self.memory[(self.counter + 1) % MAX_MEMORY],
+        ]))
+    }
+
     pub fn process(&mut self) -> anyhow::Result<()> {
         debug!("PROGRAM COUNTER: {:X?}", self.counter);
 
-        let instruction = AhoyInstruction::from(self.fetch());
+        let pc = self.counter;
+        let opcode = self.fetch();
+        let instruction = AhoyInstruction::from(opcode);
         debug!("FETCHED: {:?}", instruction);
 
+        if let Some(sanitizer) = &mut self.sanitizer {
+            sanitizer.check_instruction(pc, opcode, &instruction, self.index, self.stack.len());
+        }
+
+        if let AhoyInstruction::WaitForKey(register_addr) = instruction {
+            if !self.wait_for_key(register_addr) {
+                self.counter = pc;
+            }
+            return Ok(());
+        }
         self.execute(instruction)?;
 
         Ok(())
@@ -76,28 +203,213 @@ impl Ahoy {
 
     fn fetch(&mut self) -> u16 {
         let first_nibble = self.memory[self.counter] as u16;
-        let second_nibble = self.memory[self.counter + 1] as u16;
+        let second_nibble = self.memory[(self.counter + 1) % MAX_MEMORY] as u16;
         debug!("FETCH > FIRST NIBBLE: {:X?}", first_nibble);
         debug!("FETCH > SECOND NIBBLE: {:X?}", second_nibble);
 
         let instruction = (first_nibble << 8) | second_nibble;
         debug!("FETCH > INSTRUCTION: {:X?}", instruction);
 
-        self.counter = ((self.counter + 2) % MAX_MEMORY).max(PROGRAM_MEMORY_START);
+        self.skip_instruction();
         instruction
     }
 
+    fn skip_instruction(&mut self) {
+        self.counter = ((self.counter + 2) % MAX_MEMORY).max(PROGRAM_MEMORY_START);
+    }
+
+    // Like the COSMAC VIP, finish on release so one press isn't read twice.
+    // Returns whether the key was stored
+    fn wait_for_key(&mut self, register_addr: usize) -> bool {
+        match self.awaited_key {
+            Some(key) if self.keypad & (1 << key) == 0 => {
+                self.registers[register_addr] = key;
+                self.awaited_key = None;
+                true
+            }
+            _ => {
+                if self.awaited_key.is_none() && self.keypad != 0 {
+                    self.awaited_key = Some(self.keypad.trailing_zeros() as u8);
+                }
+                false
+            }
+        }
+    }
+
+    // Only the low nibble of VX names a key
+    fn key_pressed(&self, register_addr: usize) -> bool {
+        self.keypad & (1 << (self.registers[register_addr] & 0xF)) != 0
+    }
+
+    fn skip_if(&mut self, condition: bool) {
+        if condition {
+            self.skip_instruction();
+        }
+    }
+
+    // Original CHIP-8 leaves I just past the registers it stored or loaded
+    fn advance_index_past(&mut self, last_register: usize) {
+        if self.quirks.memory_increment {
+            self.index = (self.index + last_register + 1) % MAX_MEMORY;
+        }
+    }
+
+    // Original CHIP-8 clobbers VF in the logic instructions
+    fn reset_flag_after_logic(&mut self) {
+        if self.quirks.vf_reset {
+            self.registers[FLAG_REGISTER] = 0;
+        }
+    }
+
+    // Without the shifting quirk VY is shifted into VX
+    fn shift_source(&self, x_register: usize, y_register: usize) -> u8 {
+        match self.quirks.shifting {
+            true => self.registers[x_register],
+            false => self.registers[y_register],
+        }
+    }
+
+    fn next_random(&mut self) -> u8 {
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 7;
+        self.rng_state ^= self.rng_state << 17;
+        (self.rng_state >> 32) as u8
+    }
+
     fn execute(&mut self, instruction: AhoyInstruction) -> anyhow::Result<()> {
         match instruction {
             AhoyInstruction::ClearScreen => {
-                self.current_frame = [0; DISPLAY_HEIGHT];
+                if self.current_frame != [0; DISPLAY_HEIGHT] {
+                    self.current_frame = [0; DISPLAY_HEIGHT];
+                    self.frame_generation += 1;
+                }
             }
             AhoyInstruction::Jump(addr) => {
                 self.counter = addr;
             }
+            AhoyInstruction::CallSubroutine(addr) => {
+                if self.stack.len() >= MAX_STACK_DEPTH {
+                    return Err(anyhow!("Stack overflow calling 0x{:03X}", addr));
+                }
+                self.stack.push_back(self.counter as u16);
+                self.counter = addr as usize;
+            }
+            AhoyInstruction::StopSubroutine => {
+                let addr = self
+                    .stack
+                    .pop_back()
+                    .ok_or_else(|| anyhow!("Returned from a subroutine with an empty stack"))?;
+                self.counter = addr as usize % MAX_MEMORY;
+            }
+            AhoyInstruction::SkipIfEqual(register_addr, value) => {
+                self.skip_if(self.registers[register_addr] == value);
+            }
+            AhoyInstruction::SkipIfNotEqual(register_addr, value) => {
+                self.skip_if(self.registers[register_addr] != value);
+            }
+            AhoyInstruction::SkipIfRegistersEqual(x, y) => {
+                self.skip_if(self.registers[x] == self.registers[y]);
+            }
+            AhoyInstruction::SkipIfRegistersNotEqual(x, y) => {
+                self.skip_if(self.registers[x] != self.registers[y]);
+            }
+            AhoyInstruction::SkipIfKeyPressed(register_addr) => {
+                self.skip_if(self.key_pressed(register_addr));
+            }
+            AhoyInstruction::SkipIfKeyNotPressed(register_addr) => {
+                self.skip_if(!self.key_pressed(register_addr));
+            }
             AhoyInstruction::SetIndex(value) => {
                 self.index = value as usize;
             }
+            AhoyInstruction::JumpWithOffset(addr) => {
+                let offset_register = match self.quirks.jumping {
+                    true => ((addr >> 8) & 0xF) as usize,
+                    false => 0,
+                };
+                self.counter =
+                    (addr as usize + self.registers[offset_register] as usize) % MAX_MEMORY;
+            }
+            AhoyInstruction::CopyRegister(x, y) => {
+                self.registers[x] = self.registers[y];
+            }
+            AhoyInstruction::Or(x, y) => {
+                self.registers[x] |= self.registers[y];
+                self.reset_flag_after_logic();
+            }
+            AhoyInstruction::And(x, y) => {
+                self.registers[x] &= self.registers[y];
+                self.reset_flag_after_logic();
+            }
+            AhoyInstruction::Xor(x, y) => {
+                self.registers[x] ^= self.registers[y];
+                self.reset_flag_after_logic();
+            }
+            // The flag is written last, so it wins when VF is also the target
+            AhoyInstruction::AddRegisters(x, y) => {
+                let (sum, carry) = self.registers[x].overflowing_add(self.registers[y]);
+                self.registers[x] = sum;
+                self.registers[FLAG_REGISTER] = carry as u8;
+            }
+            AhoyInstruction::Subtract(x, y) => {
+                let (difference, borrow) = self.registers[x].overflowing_sub(self.registers[y]);
+                self.registers[x] = difference;
+                self.registers[FLAG_REGISTER] = !borrow as u8;
+            }
+            AhoyInstruction::SubtractReversed(x, y) => {
+                let (difference, borrow) = self.registers[y].overflowing_sub(self.registers[x]);
+                self.registers[x] = difference;
+                self.registers[FLAG_REGISTER] = !borrow as u8;
+            }
+            AhoyInstruction::ShiftRight(x, y) => {
+                let value = self.shift_source(x, y);
+                self.registers[x] = value >> 1;
+                self.registers[FLAG_REGISTER] = value & 1;
+            }
+            AhoyInstruction::ShiftLeft(x, y) => {
+                let value = self.shift_source(x, y);
+                self.registers[x] = value << 1;
+                self.registers[FLAG_REGISTER] = value >> 7;
+            }
+            AhoyInstruction::ReadDelayTimer(register_addr) => {
+                self.registers[register_addr] = self.delay_timer;
+            }
+            AhoyInstruction::SetDelayTimer(register_addr) => {
+                self.delay_timer = self.registers[register_addr];
+            }
+            AhoyInstruction::SetSoundTimer(register_addr) => {
+                self.sound_timer = self.registers[register_addr];
+            }
+            AhoyInstruction::AddToIndex(register_addr) => {
+                self.index = (self.index + self.registers[register_addr] as usize) % MAX_MEMORY;
+            }
+            AhoyInstruction::FontCharacter(register_addr) => {
+                let digit = (self.registers[register_addr] & 0xF) as usize;
+                self.index = FONT_START + digit * FONT_CHARACTER_HEIGHT;
+            }
+            AhoyInstruction::StoreDecimal(register_addr) => {
+                let value = self.registers[register_addr];
+                for (offset, digit) in [value / 100, value / 10 % 10, value % 10]
+                    .into_iter()
+                    .enumerate()
+                {
+                    self.memory[(self.index + offset) % MAX_MEMORY] = digit;
+                }
+            }
+            AhoyInstruction::StoreRegisters(last_register) => {
+                for register_addr in 0..=last_register {
+                    self.memory[(self.index + register_addr) % MAX_MEMORY] =
+                        self.registers[register_addr];
+                }
+                self.advance_index_past(last_register);
+            }
+            AhoyInstruction::LoadRegisters(last_register) => {
+                for register_addr in 0..=last_register {
+                    self.registers[register_addr] =
+                        self.memory[(self.index + register_addr) % MAX_MEMORY];
+                }
+                self.advance_index_past(last_register);
+            }
             AhoyInstruction::SetRegister(register_addr, value) => {
                 self.registers[register_addr] = value;
             }
@@ -105,6 +417,13 @@ impl Ahoy {
                 let prev_value = self.registers[register_addr];
                 self.registers[register_addr] = prev_value.wrapping_add(value);
             }
+            AhoyInstruction::Random(register_addr, mask) => {
+                self.registers[register_addr] = self.next_random() & mask;
+            }
+            // `process` keeps the program counter on FX0A until it finishes
+            AhoyInstruction::WaitForKey(register_addr) => {
+                self.wait_for_key(register_addr);
+            }
             AhoyInstruction::Display {
                 x_register,
                 y_register,
@@ -112,24 +431,38 @@ impl Ahoy {
             } => {
                 self.registers[FLAG_REGISTER] = 0;
 
-                let sprite_end = self.index + sprite_height as usize;
-
                 let row = self.registers[y_register] as usize % DISPLAY_HEIGHT;
                 let col = self.registers[x_register] as usize % DISPLAY_WIDTH;
-                let col_offset = DISPLAY_WIDTH - SPRITE_WIDTH - col;
 
                 debug!("EXECUTE > DRAWING > ROW: {}, COL: {}", row, col);
 
-                for (row_offset, sprite_row) in
-                    self.memory[self.index..sprite_end].iter().enumerate()
-                {
-                    let curr_row = row + row_offset;
+                let mut changed = false;
+                for row_offset in 0..sprite_height as usize {
+                    let mut curr_row = row + row_offset;
+                    if curr_row >= DISPLAY_HEIGHT {
+                        if self.quirks.clipping {
+                            break;
+                        }
+                        curr_row %= DISPLAY_HEIGHT;
+                    }
 
-                    let prev_zero_count = self.current_frame[curr_row].count_zeros();
-                    self.current_frame[curr_row] ^= (*sprite_row as u64) << col_offset;
-                    if self.current_frame[curr_row].count_zeros() > prev_zero_count {
+                    // Sprites reaching past the end of memory wrap around to its start
+                    let sprite_row = self.memory[(self.index + row_offset) % MAX_MEMORY] as u64;
+                    let aligned_row = sprite_row << (DISPLAY_WIDTH - SPRITE_WIDTH);
+                    let sprite_bits = if self.quirks.clipping {
+                        aligned_row >> col
+                    } else {
+                        aligned_row.rotate_right(col as u32)
+                    };
+
+                    if self.current_frame[curr_row] & sprite_bits != 0 {
                         self.registers[FLAG_REGISTER] = 1;
                     }
+                    self.current_frame[curr_row] ^= sprite_bits;
+                    changed |= sprite_bits != 0;
+                }
+                if changed {
+                    self.frame_generation += 1;
                 }
             }
             _ => debug!("Ignoring this instruction: {:X?}", instruction),
@@ -143,8 +476,11 @@ mod tests {
     use std::io::{BufReader, Cursor};
 
     use crate::{
-        Ahoy, FLAG_REGISTER, constants::PROGRAM_MEMORY_START, display::DISPLAY_HEIGHT,
+        Ahoy, FLAG_REGISTER,
+        constants::{MAX_MEMORY, MAX_STACK_DEPTH, PROGRAM_MEMORY_START},
+        display::DISPLAY_HEIGHT,
         instructions::AhoyInstruction,
+        quirks::{Platform, Quirks},
     };
 
     #[test]
@@ -168,6 +504,19 @@ mod tests {
         assert_eq!(ahoy.memory[0x209], 0x0A);
     }
 
+    #[test]
+    fn load_keeps_the_configured_quirks_for_known_roms() {
+        let mut ahoy = Ahoy {
+            quirks: Platform::SuperChip.into(),
+            ..Default::default()
+        };
+        let rom = include_bytes!("../assets/roms/ibm.ch8");
+
+        ahoy.load(&mut BufReader::new(Cursor::new(rom))).unwrap();
+
+        assert_eq!(ahoy.quirks, Quirks::from(Platform::SuperChip));
+    }
+
     #[test]
     fn load_returns_error_for_empty_file() {
         let mut ahoy = Ahoy::default();
@@ -255,6 +604,43 @@ mod tests {
         assert_eq!(ahoy.counter, 0x0DAD);
     }
 
+    #[test]
+    fn instruction_call_and_return_use_the_stack() {
+        let mut ahoy = Ahoy {
+            counter: 0x204,
+            ..Default::default()
+        };
+
+        ahoy.execute(AhoyInstruction::CallSubroutine(0x300))
+            .unwrap();
+        assert_eq!(ahoy.counter, 0x300);
+        assert_eq!(ahoy.stack, [0x204]);
+
+        ahoy.execute(AhoyInstruction::StopSubroutine).unwrap();
+        assert_eq!(ahoy.counter, 0x204);
+        assert!(ahoy.stack.is_empty());
+    }
+
+    #[test]
+    fn instruction_return_with_empty_stack_is_an_error() {
+        let mut ahoy = Ahoy::default();
+
+        ahoy.execute(AhoyInstruction::StopSubroutine)
+            .expect_err("Expected return with an empty stack to raise error");
+    }
+
+    #[test]
+    fn instruction_call_past_the_stack_limit_is_an_error() {
+        let mut ahoy = Ahoy::default();
+        for _ in 0..MAX_STACK_DEPTH {
+            ahoy.execute(AhoyInstruction::CallSubroutine(0x200))
+                .unwrap();
+        }
+
+        ahoy.execute(AhoyInstruction::CallSubroutine(0x200))
+            .expect_err("Expected stack overflow to raise error");
+    }
+
     #[test]
     fn instruction_set_register_value_updates_value() {
         let mut ahoy = Ahoy::default();
@@ -316,6 +702,36 @@ mod tests {
         assert_eq!(ahoy.current_frame[2], 0x0000000000000000);
     }
 
+    #[test]
+    fn frame_generation_only_moves_when_the_frame_changes() {
+        let mut ahoy = Ahoy::default();
+        ahoy.memory[PROGRAM_MEMORY_START] = 0xFF;
+
+        ahoy.execute(AhoyInstruction::ClearScreen).unwrap();
+        assert_eq!(ahoy.frame_generation(), 0);
+
+        ahoy.index = PROGRAM_MEMORY_START + 1;
+        ahoy.execute(AhoyInstruction::Display {
+            x_register: 0,
+            y_register: 0,
+            sprite_height: 1,
+        })
+        .unwrap();
+        assert_eq!(ahoy.frame_generation(), 0);
+
+        ahoy.index = PROGRAM_MEMORY_START;
+        ahoy.execute(AhoyInstruction::Display {
+            x_register: 0,
+            y_register: 0,
+            sprite_height: 1,
+        })
+        .unwrap();
+        assert_eq!(ahoy.frame_generation(), 1);
+
+        ahoy.execute(AhoyInstruction::ClearScreen).unwrap();
+        assert_eq!(ahoy.frame_generation(), 2);
+    }
+
     #[test]
     fn instruction_display_treats_sprite_zero_bits_as_transparent() {
         let mut ahoy = Ahoy::default();
@@ -398,6 +814,103 @@ mod tests {
         assert_eq!(ahoy.current_frame[0], 0xFFFFFFFFFFFFFFFF);
     }
 
+    #[test]
+    fn instruction_display_flags_collisions_that_keep_the_pixel_count() {
+        let mut ahoy = Ahoy::default();
+        ahoy.memory[PROGRAM_MEMORY_START] = 0b1100_0000;
+        ahoy.current_frame[0] = 0x4000000000000000;
+
+        ahoy.execute(AhoyInstruction::Display {
+            x_register: 0,
+            y_register: 0,
+            sprite_height: 1,
+        })
+        .unwrap();
+
+        assert_eq!(ahoy.registers[FLAG_REGISTER], 1);
+        assert_eq!(ahoy.current_frame[0], 0x8000000000000000);
+    }
+
+    #[test]
+    fn instruction_display_clips_sprites_at_the_edges() {
+        let mut ahoy = Ahoy {
+            quirks: Quirks {
+                clipping: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        ahoy.memory[PROGRAM_MEMORY_START..PROGRAM_MEMORY_START + 4].fill(0xFF);
+        ahoy.registers[0x0] = 60;
+        ahoy.registers[0x1] = 30;
+
+        ahoy.execute(AhoyInstruction::Display {
+            x_register: 0,
+            y_register: 1,
+            sprite_height: 4,
+        })
+        .unwrap();
+
+        assert_eq!(ahoy.current_frame[30], 0x000000000000000F);
+        assert_eq!(ahoy.current_frame[31], 0x000000000000000F);
+        assert_eq!(ahoy.current_frame[0], 0);
+        assert_eq!(ahoy.current_frame[1], 0);
+    }
+
+    #[test]
+    fn instruction_display_wraps_sprites_without_clipping() {
+        let mut ahoy = Ahoy::default();
+        ahoy.memory[PROGRAM_MEMORY_START..PROGRAM_MEMORY_START + 4].fill(0xFF);
+        ahoy.registers[0x0] = 60;
+        ahoy.registers[0x1] = 30;
+
+        ahoy.execute(AhoyInstruction::Display {
+            x_register: 0,
+            y_register: 1,
+            sprite_height: 4,
+        })
+        .unwrap();
+
+        assert_eq!(ahoy.current_frame[30], 0xF00000000000000F);
+        assert_eq!(ahoy.current_frame[31], 0xF00000000000000F);
+        assert_eq!(ahoy.current_frame[0], 0xF00000000000000F);
+        assert_eq!(ahoy.current_frame[1], 0xF00000000000000F);
+    }
+
+    #[test]
+    fn instruction_display_wraps_sprite_data_past_the_end_of_memory() {
+        let mut ahoy = Ahoy {
+            index: MAX_MEMORY - 1,
+            ..Default::default()
+        };
+        ahoy.memory[MAX_MEMORY - 1] = 0xAA;
+        ahoy.memory[0] = 0x55;
+
+        ahoy.execute(AhoyInstruction::Display {
+            x_register: 0,
+            y_register: 0,
+            sprite_height: 2,
+        })
+        .unwrap();
+
+        assert_eq!(ahoy.current_frame[0], 0xAA00000000000000);
+        assert_eq!(ahoy.current_frame[1], 0x5500000000000000);
+    }
+
+    #[test]
+    fn process_at_the_last_byte_of_memory_wraps_the_fetch() {
+        let mut ahoy = Ahoy {
+            counter: MAX_MEMORY - 1,
+            ..Default::default()
+        };
+        ahoy.memory[MAX_MEMORY - 1] = 0x12;
+        ahoy.memory[0] = 0x34;
+
+        ahoy.process().unwrap();
+
+        assert_eq!(ahoy.counter, 0x234);
+    }
+
     #[test]
     fn instruction_set_index_updates_its_value() {
         let mut ahoy = Ahoy::default();
@@ -405,4 +918,315 @@ mod tests {
 
         assert_eq!(ahoy.index, 1023_usize);
     }
+
+    #[test]
+    fn instruction_random_is_masked_and_deterministic_for_a_seed() {
+        let mut first = Ahoy::default();
+        let mut second = Ahoy::default();
+        first.seed_rng(1234);
+        second.seed_rng(1234);
+
+        for _ in 0..32 {
+            first.execute(AhoyInstruction::Random(0x1, 0x0F)).unwrap();
+            second.execute(AhoyInstruction::Random(0x1, 0x0F)).unwrap();
+
+            assert_eq!(first.registers[0x1], second.registers[0x1]);
+            assert_eq!(first.registers[0x1] & 0xF0, 0);
+        }
+    }
+
+    #[test]
+    fn run_frame_processes_instructions_and_ticks_timers() {
+        let mut ahoy = Ahoy {
+            delay_timer: 2,
+            sound_timer: 1,
+            ..Default::default()
+        };
+
+        ahoy.run_frame(5).unwrap();
+        assert_eq!(ahoy.counter, PROGRAM_MEMORY_START + 10);
+        assert_eq!((ahoy.delay_timer, ahoy.sound_timer), (1, 0));
+
+        ahoy.run_frame(0).unwrap();
+        assert_eq!((ahoy.delay_timer, ahoy.sound_timer), (0, 0));
+    }
+
+    #[test]
+    fn is_idle_detects_jumps_to_self() {
+        let mut ahoy = Ahoy::default();
+        ahoy.memory[0x200..0x204].copy_from_slice(&[0x12, 0x02, 0x12, 0x02]);
+
+        assert!(!ahoy.is_idle());
+        ahoy.process().unwrap();
+        assert!(ahoy.is_idle());
+    }
+
+    #[test]
+    fn wait_for_key_stores_the_key_once_released() {
+        let mut ahoy = Ahoy::default();
+        ahoy.memory[0x200..0x202].copy_from_slice(&[0xF3, 0x0A]);
+        assert_eq!(ahoy.awaiting_key(), Some(0x3));
+
+        ahoy.run_frame(4).unwrap();
+        assert_eq!(ahoy.counter, PROGRAM_MEMORY_START);
+
+        ahoy.set_key(0xB, true);
+        ahoy.run_frame(4).unwrap();
+        assert_eq!(ahoy.counter, PROGRAM_MEMORY_START);
+
+        ahoy.set_key(0xB, false);
+        ahoy.process().unwrap();
+        assert_eq!(ahoy.counter, PROGRAM_MEMORY_START + 2);
+        assert_eq!(ahoy.registers[0x3], 0xB);
+        assert_eq!(ahoy.awaiting_key(), None);
+    }
+
+    #[test]
+    fn wait_for_key_at_the_end_of_memory_stays_put() {
+        let mut ahoy = Ahoy {
+            counter: 0xFFE,
+            ..Default::default()
+        };
+        ahoy.memory[0xFFE..].copy_from_slice(&[0xF3, 0x0A]);
+
+        ahoy.run_frame(4).unwrap();
+        assert_eq!(ahoy.counter, 0xFFE);
+        assert_eq!(ahoy.awaiting_key(), Some(0x3));
+    }
+
+    #[test]
+    fn set_key_toggles_keypad_bits() {
+        let mut ahoy = Ahoy::default();
+
+        ahoy.set_key(0x0, true);
+        ahoy.set_key(0xF, true);
+        assert_eq!(ahoy.keypad(), 0b1000_0000_0000_0001);
+
+        ahoy.set_key(0x0, false);
+        assert_eq!(ahoy.keypad(), 0b1000_0000_0000_0000);
+    }
+
+    #[test]
+    fn key_skip_instructions_read_the_keypad() {
+        let mut ahoy = Ahoy::default();
+        ahoy.registers[0x1] = 0x1A;
+        ahoy.set_key(0xA, true);
+
+        ahoy.execute(AhoyInstruction::SkipIfKeyPressed(0x1))
+            .unwrap();
+        assert_eq!(ahoy.counter, PROGRAM_MEMORY_START + 2);
+        ahoy.execute(AhoyInstruction::SkipIfKeyNotPressed(0x1))
+            .unwrap();
+        assert_eq!(ahoy.counter, PROGRAM_MEMORY_START + 2);
+
+        ahoy.set_key(0xA, false);
+        ahoy.execute(AhoyInstruction::SkipIfKeyPressed(0x1))
+            .unwrap();
+        assert_eq!(ahoy.counter, PROGRAM_MEMORY_START + 2);
+        ahoy.execute(AhoyInstruction::SkipIfKeyNotPressed(0x1))
+            .unwrap();
+        assert_eq!(ahoy.counter, PROGRAM_MEMORY_START + 4);
+    }
+
+    #[test]
+    fn skip_instructions_compare_registers_and_values() {
+        let mut ahoy = Ahoy::default();
+        ahoy.registers[0x1] = 0x42;
+        ahoy.registers[0x2] = 0x42;
+
+        ahoy.execute(AhoyInstruction::SkipIfEqual(0x1, 0x42))
+            .unwrap();
+        assert_eq!(ahoy.counter, PROGRAM_MEMORY_START + 2);
+        ahoy.execute(AhoyInstruction::SkipIfNotEqual(0x1, 0x42))
+            .unwrap();
+        assert_eq!(ahoy.counter, PROGRAM_MEMORY_START + 2);
+        ahoy.execute(AhoyInstruction::SkipIfRegistersEqual(0x1, 0x2))
+            .unwrap();
+        assert_eq!(ahoy.counter, PROGRAM_MEMORY_START + 4);
+        ahoy.execute(AhoyInstruction::SkipIfRegistersNotEqual(0x1, 0x3))
+            .unwrap();
+        assert_eq!(ahoy.counter, PROGRAM_MEMORY_START + 6);
+    }
+
+    #[test]
+    fn logic_instructions_reset_vf_only_with_the_quirk() {
+        for vf_reset in [false, true] {
+            let mut ahoy = Ahoy {
+                quirks: Quirks {
+                    vf_reset,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            ahoy.registers[0x1] = 0b1100;
+            ahoy.registers[0x2] = 0b1010;
+            ahoy.registers[FLAG_REGISTER] = 7;
+
+            ahoy.execute(AhoyInstruction::Or(0x1, 0x2)).unwrap();
+            assert_eq!(ahoy.registers[0x1], 0b1110);
+            ahoy.execute(AhoyInstruction::And(0x1, 0x2)).unwrap();
+            assert_eq!(ahoy.registers[0x1], 0b1010);
+            ahoy.execute(AhoyInstruction::Xor(0x1, 0x2)).unwrap();
+            assert_eq!(ahoy.registers[0x1], 0);
+            assert_eq!(ahoy.registers[FLAG_REGISTER], if vf_reset { 0 } else { 7 });
+        }
+    }
+
+    #[test]
+    fn arithmetic_instructions_set_carry_and_borrow_flags() {
+        let mut ahoy = Ahoy::default();
+        ahoy.registers[0x1] = 0xF0;
+        ahoy.registers[0x2] = 0x20;
+
+        ahoy.execute(AhoyInstruction::AddRegisters(0x1, 0x2))
+            .unwrap();
+        assert_eq!(
+            (ahoy.registers[0x1], ahoy.registers[FLAG_REGISTER]),
+            (0x10, 1)
+        );
+        ahoy.execute(AhoyInstruction::Subtract(0x1, 0x2)).unwrap();
+        assert_eq!(
+            (ahoy.registers[0x1], ahoy.registers[FLAG_REGISTER]),
+            (0xF0, 0)
+        );
+        ahoy.execute(AhoyInstruction::SubtractReversed(0x1, 0x2))
+            .unwrap();
+        assert_eq!(
+            (ahoy.registers[0x1], ahoy.registers[FLAG_REGISTER]),
+            (0x30, 0)
+        );
+        ahoy.execute(AhoyInstruction::Subtract(0x1, 0x2)).unwrap();
+        assert_eq!(
+            (ahoy.registers[0x1], ahoy.registers[FLAG_REGISTER]),
+            (0x10, 1)
+        );
+
+        // The flag wins over the result when VF is the target
+        ahoy.registers[FLAG_REGISTER] = 0xFF;
+        ahoy.execute(AhoyInstruction::AddRegisters(FLAG_REGISTER, 0x2))
+            .unwrap();
+        assert_eq!(ahoy.registers[FLAG_REGISTER], 1);
+    }
+
+    #[test]
+    fn shift_instructions_read_vy_unless_the_quirk_is_set() {
+        for (shifting, expected) in [(false, 0b0100_0000), (true, 0b0000_0001)] {
+            let mut ahoy = Ahoy {
+                quirks: Quirks {
+                    shifting,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            ahoy.registers[0x1] = 0b0000_0011;
+            ahoy.registers[0x2] = 0b1000_0001;
+
+            ahoy.execute(AhoyInstruction::ShiftRight(0x1, 0x2)).unwrap();
+            assert_eq!(ahoy.registers[0x1], expected);
+            assert_eq!(ahoy.registers[FLAG_REGISTER], 1);
+        }
+
+        let mut ahoy = Ahoy::default();
+        ahoy.registers[0x2] = 0b1000_0001;
+        ahoy.execute(AhoyInstruction::ShiftLeft(0x1, 0x2)).unwrap();
+        assert_eq!(ahoy.registers[0x1], 0b0000_0010);
+        assert_eq!(ahoy.registers[FLAG_REGISTER], 1);
+    }
+
+    #[test]
+    fn jump_with_offset_reads_v0_unless_the_quirk_is_set() {
+        for (jumping, expected) in [(false, 0x312), (true, 0x320)] {
+            let mut ahoy = Ahoy {
+                quirks: Quirks {
+                    jumping,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            ahoy.registers[0x0] = 0x12;
+            ahoy.registers[0x3] = 0x20;
+
+            ahoy.execute(AhoyInstruction::JumpWithOffset(0x300))
+                .unwrap();
+            assert_eq!(ahoy.counter, expected);
+        }
+    }
+
+    #[test]
+    fn store_and_load_registers_advance_i_only_with_the_quirk() {
+        for (memory_increment, expected_index) in [(false, 0x300), (true, 0x303)] {
+            let mut ahoy = Ahoy {
+                index: 0x300,
+                quirks: Quirks {
+                    memory_increment,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            ahoy.registers[..3].copy_from_slice(&[1, 2, 3]);
+
+            ahoy.execute(AhoyInstruction::StoreRegisters(0x2)).unwrap();
+            assert_eq!(ahoy.memory[0x300..0x304], [1, 2, 3, 0]);
+            assert_eq!(ahoy.index, expected_index);
+
+            ahoy.index = 0x301;
+            ahoy.execute(AhoyInstruction::LoadRegisters(0x1)).unwrap();
+            assert_eq!(ahoy.registers[..3], [2, 3, 3]);
+        }
+    }
+
+    #[test]
+    fn index_instructions_point_at_digits_and_fonts() {
+        let mut ahoy = Ahoy {
+            index: 0xFFE,
+            ..Default::default()
+        };
+        ahoy.registers[0x4] = 234;
+
+        ahoy.execute(AhoyInstruction::StoreDecimal(0x4)).unwrap();
+        assert_eq!(
+            [ahoy.memory[0xFFE], ahoy.memory[0xFFF], ahoy.memory[0x000]],
+            [2, 3, 4]
+        );
+
+        ahoy.execute(AhoyInstruction::AddToIndex(0x4)).unwrap();
+        assert_eq!(ahoy.index, (0xFFE + 234) % MAX_MEMORY);
+
+        ahoy.registers[0x5] = 0x1A;
+        ahoy.execute(AhoyInstruction::FontCharacter(0x5)).unwrap();
+        assert_eq!(ahoy.index, 0x050 + 0xA * 5);
+    }
+
+    #[test]
+    fn timer_instructions_copy_between_registers_and_timers() {
+        let mut ahoy = Ahoy::default();
+        ahoy.registers[0x1] = 30;
+
+        ahoy.execute(AhoyInstruction::SetDelayTimer(0x1)).unwrap();
+        ahoy.execute(AhoyInstruction::SetSoundTimer(0x1)).unwrap();
+        ahoy.tick_timers();
+        ahoy.execute(AhoyInstruction::ReadDelayTimer(0x2)).unwrap();
+
+        assert_eq!(ahoy.registers[0x2], 29);
+        assert_eq!(ahoy.sound_timer, 29);
+    }
+
+    #[test]
+    fn display_wait_ends_the_frame_after_a_sprite() {
+        for (display_wait, expected_counter) in [(false, 0x208), (true, 0x204)] {
+            let mut ahoy = Ahoy {
+                quirks: Quirks {
+                    display_wait,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            // ADD V0,1 / DRW V1,V1,1 / ADD V0,1 / ADD V0,1
+            ahoy.memory[0x200..0x208]
+                .copy_from_slice(&[0x70, 0x01, 0xD1, 0x11, 0x70, 0x01, 0x70, 0x01]);
+
+            ahoy.run_frame(4).unwrap();
+            assert_eq!(ahoy.counter, expected_counter);
+        }
+    }
 }
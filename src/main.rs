@@ -1,42 +1,935 @@
-mod display;
-
-use std::{fs::File, io::BufReader, path::PathBuf, time::Duration};
-
-use ahoy::Ahoy;
-use cli_log::init_cli_log;
-use crossterm::event::{self, Event};
-use display::{AhoyDisplay, DISPLAY_HEIGHT, RatatuiAhoyDisplay};
-use ratatui::{
-    self,
-    style::Color,
-    widgets::canvas::{Canvas, Rectangle},
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use clap::Parser;
+use ahoy::{
+    Ahoy,
+    animation::{AnimationFormat, AnimationRecorder},
+    cast::{CastTee, CastWriter},
+    config::{Config, RomSettings},
+    controls::{Control, Controls},
+    database::{RomInfo, sha1_hex},
+    debugger::{DebugCommand, Debugger},
+    display::{AhoyDisplay, KeypadView, RatatuiAhoyDisplay, Renderer, frame_hash},
+    export::{ImageFormat, write_ascii, write_image},
+    filter::{FilterMode, FrameFilter},
+    graphics::{GraphicsAhoyDisplay, GraphicsProtocol},
+    input::{HeldKey, KeyboardEnhancement},
+    inspect::{Inspection, inspect},
+    movie::Movie,
+    palette::Palette,
+    quirks::{Platform, Quirks},
+    rewind::Rewind,
+};
+use anyhow::anyhow;
+use cli_log::{info, init_cli_log, warn};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+
+use clap::{Parser, Subcommand, ValueEnum, builder::RangedU64ValueParser};
+
+const SAVE_SLOTS: u8 = 4;
+const MAX_SCALE: u64 = 64;
+const INSTRUCTIONS_PER_FRAME: usize = 11;
+const FRAME_DURATION: Duration = Duration::from_micros(16_667);
+// Without release events a held key only shows up as auto-repeats
+const REWIND_HOLD: Duration = Duration::from_millis(250);
 
 #[derive(Parser)]
-struct Args {
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a program in the terminal, or headless with --headless
+    Run(Box<RunArgs>),
+    /// Describe a ROM from the built-in database and its reachable code
+    Info(InfoArgs),
+    /// Step through a program forwards and backwards from a command prompt
+    Debug(DebugArgs),
+}
+
+#[derive(clap::Args)]
+struct InfoArgs {
     #[arg()]
     program: PathBuf,
 }
-fn main() -> anyhow::Result<()> {
-    init_cli_log!();
 
-    let args = Args::parse();
-    let file = File::open(args.program)?;
-    let mut reader = BufReader::new(file);
+#[derive(clap::Args)]
+struct DebugArgs {
+    #[arg()]
+    program: PathBuf,
+    /// Quirk preset, overriding the database and the guess from the code
+    #[arg(long)]
+    platform: Option<Platform>,
+    /// Instructions run per 60 Hz frame, which paces the timers [default: 11]
+    #[arg(long)]
+    instructions_per_frame: Option<usize>,
+    /// Instructions between checkpoints used to step backwards
+    #[arg(long, default_value_t = 1000)]
+    checkpoint_interval: u64,
+    /// Memory budget for checkpoints, in MiB
+    #[arg(long, default_value_t = 64)]
+    checkpoint_budget: usize,
+}
+
+#[derive(clap::Args)]
+struct RunArgs {
+    #[arg()]
+    program: PathBuf,
+    /// Frames between rewind snapshots
+    #[arg(long, default_value_t = 2)]
+    rewind_interval: usize,
+    /// Memory budget for rewind snapshots, in MiB
+    #[arg(long, default_value_t = 16)]
+    rewind_budget: usize,
+    /// Record keypad input into a movie file
+    #[arg(long, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+    /// Replay keypad input from a movie file
+    #[arg(long)]
+    replay: Option<PathBuf>,
+    /// Run without a terminal and print the final frame hash
+    #[arg(long, conflicts_with = "record")]
+    headless: bool,
+    /// Frames to run headless, defaults to the length of the replayed movie
+    #[arg(long, requires = "headless")]
+    frames: Option<u64>,
+    /// Write the final frame to this file, `-` for stdout
+    #[arg(long, requires = "headless")]
+    output: Option<PathBuf>,
+    /// Image format used for --output
+    #[arg(long, value_enum, default_value_t = DumpFormat::Ascii)]
+    format: DumpFormat,
+    /// Pixel size of PBM and PNG frames written by --output
+    #[arg(long, default_value_t = 1, requires = "headless", value_parser = scale_parser())]
+    scale: usize,
+    /// Pixel size of screenshots taken with F12
+    #[arg(long, default_value_t = 8, value_parser = scale_parser())]
+    screenshot_scale: usize,
+    /// Record an animated GIF or APNG from the start, picked by extension
+    #[arg(long)]
+    capture: Option<PathBuf>,
+    /// Pixel size of animations recorded with --capture or F11
+    #[arg(long, default_value_t = 4, value_parser = scale_parser())]
+    capture_scale: usize,
+    /// Fail the headless run unless the final frame hash matches
+    #[arg(long, requires = "headless", value_parser = parse_hash)]
+    expect_hash: Option<u64>,
+    /// Report suspicious program behaviour on exit
+    #[arg(long)]
+    sanitize: bool,
+    /// How pixels are drawn in the terminal [default: auto]
+    #[arg(long, value_enum)]
+    renderer: Option<RendererChoice>,
+    /// Draw with a terminal image protocol instead of text cells; `auto` detects one.
+    /// Images show the screen only, without status line, pause menu or keypad
+    #[arg(long, value_enum, default_value_t = DisplayChoice::Text)]
+    display: DisplayChoice,
+    /// Palette name (classic, amber, green, octo, high-contrast) or 2-4 comma separated hex colours [default: classic]
+    #[arg(long)]
+    palette: Option<Palette>,
+    /// Anti-flicker filter: off, blend[:N] or phosphor[:N] over N frames [default: off]
+    #[arg(long)]
+    filter: Option<FilterMode>,
+    /// Quirk preset: chip8, schip or xochip [default: none]
+    #[arg(long)]
+    platform: Option<Platform>,
+    /// Instructions run per 60 Hz frame [default: 11]
+    #[arg(long)]
+    instructions_per_frame: Option<usize>,
+    /// Per-ROM settings file, which these flags override [default: ~/.config/ahoy/config.toml]
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Record the text display as an asciinema v2 cast
+    #[arg(long, conflicts_with = "headless")]
+    cast: Option<PathBuf>,
+    /// Milliseconds a key stays held after its last press, on terminals that
+    /// cannot report key releases
+    #[arg(long, default_value_t = 150)]
+    key_hold: u64,
+    /// Show the CHIP-8 keypad beside the game, toggled with K
+    #[arg(long)]
+    keypad: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DisplayChoice {
+    Text,
+    Auto,
+    Sixel,
+    Kitty,
+}
+
+impl DisplayChoice {
+    fn protocol(self) -> Option<GraphicsProtocol> {
+        match self {
+            DisplayChoice::Auto => GraphicsProtocol::detect(),
+            DisplayChoice::Text => None,
+            DisplayChoice::Sixel => Some(GraphicsProtocol::Sixel),
+            DisplayChoice::Kitty => Some(GraphicsProtocol::Kitty),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum RendererChoice {
+    Auto,
+    Block,
+    HalfBlock,
+    Braille,
+}
+
+// The config file and command line merged, with the flags winning
+struct Settings {
+    quirks: Quirks,
+    instructions_per_frame: usize,
+    palette: Palette,
+    filter: FilterMode,
+    renderer: Option<Renderer>,
+    keymap: [char; 16],
+}
+
+impl Settings {
+    fn resolve(args: &RunArgs, rom: &[u8], info: Option<&RomInfo>) -> anyhow::Result<Self> {
+        let config = match args.config.clone().or_else(Config::default_path) {
+            Some(path) => Config::load(&path)?,
+            None => Config::default(),
+        };
+        let file_name = args
+            .program
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into());
+        let cli = RomSettings {
+            platform: args.platform,
+            instructions_per_frame: args.instructions_per_frame,
+            palette: args.palette,
+            filter: args.filter,
+            ..Default::default()
+        };
+        // ROMs missing from the database get quirks guessed from their code
+        let known = match info {
+            Some(info) => info.settings(),
+            None => inspect(rom).recommend().settings(),
+        };
+        let settings = cli.or(config.for_rom(&file_name, &sha1_hex(rom)).or(known));
+
+        Ok(Self {
+            quirks: settings.quirks(),
+            instructions_per_frame: settings
+                .instructions_per_frame
+                .unwrap_or(INSTRUCTIONS_PER_FRAME),
+            palette: settings.palette.unwrap_or_default(),
+            filter: settings.filter.unwrap_or(FilterMode::Off),
+            // `--renderer auto` also overrides a renderer from the file
+            renderer: match args.renderer {
+                Some(choice) => choice.into(),
+                None => settings.renderer,
+            },
+            keymap: settings.keymap()?,
+        })
+    }
+}
+
+impl From<RendererChoice> for Option<Renderer> {
+    fn from(choice: RendererChoice) -> Self {
+        match choice {
+            RendererChoice::Auto => None,
+            RendererChoice::Block => Some(Renderer::Block),
+            RendererChoice::HalfBlock => Some(Renderer::HalfBlock),
+            RendererChoice::Braille => Some(Renderer::Braille),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DumpFormat {
+    Ascii,
+    Pbm,
+    Png,
+}
+
+// Keeps exported images within what GIF and PNG dimensions can hold
+fn scale_parser() -> RangedU64ValueParser<usize> {
+    RangedU64ValueParser::new().range(1..=MAX_SCALE)
+}
+
+fn mebibytes(budget: usize) -> anyhow::Result<usize> {
+    budget
+        .checked_mul(1024 * 1024)
+        .ok_or_else(|| anyhow!("Memory budget of {} MiB is too large", budget))
+}
+
+fn parse_hash(value: &str) -> Result<u64, std::num::ParseIntError> {
+    u64::from_str_radix(value.trim_start_matches("0x"), 16)
+}
+
+fn keypad_key(code: KeyCode, keymap: &[char; 16]) -> Option<u8> {
+    let KeyCode::Char(c) = code else {
+        return None;
+    };
+    keymap
+        .iter()
+        .position(|host| *host == c.to_ascii_lowercase())
+        .map(|key| key as u8)
+}
+
+fn keypad_view(ahoy: &Ahoy, keymap: &[char; 16]) -> KeypadView {
+    KeypadView {
+        labels: *keymap,
+        pressed: ahoy.keypad(),
+        awaiting: ahoy.awaiting_key(),
+    }
+}
+
+fn state_slot_path(program: &Path, slot: u8) -> PathBuf {
+    let mut path = program.as_os_str().to_owned();
+    path.push(format!(".state{slot}"));
+    PathBuf::from(path)
+}
+
+fn save_slot(ahoy: &Ahoy, program: &Path, slot: u8) -> anyhow::Result<()> {
+    let mut writer = BufWriter::new(File::create(state_slot_path(program, slot))?);
+    ahoy.save_state(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn save_movie(movie: &Movie, path: &Path) -> anyhow::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    movie.write(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn load_slot(program: &Path, slot: u8) -> anyhow::Result<Ahoy> {
+    let mut reader = BufReader::new(File::open(state_slot_path(program, slot))?);
+    Ahoy::load_state(&mut reader)
+}
+
+fn dump_frame<W: Write>(
+    ahoy: &Ahoy,
+    args: &RunArgs,
+    palette: &Palette,
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    let format = match args.format {
+        DumpFormat::Ascii => return write_ascii(&ahoy.current_frame, writer),
+        DumpFormat::Pbm => ImageFormat::Pbm,
+        DumpFormat::Png => ImageFormat::Png,
+    };
+    write_image(&ahoy.current_frame, format, args.scale, palette, writer)
+}
+
+// `<rom>-YYYYMMDD-HHMMSS.<extension>` next to the ROM, in UTC
+fn timestamped_path(program: &Path, taken: SystemTime, extension: &str) -> anyhow::Result<PathBuf> {
+    let seconds = taken.duration_since(UNIX_EPOCH)?.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let time = seconds % 86_400;
+    let stem = program
+        .file_stem()
+        .map_or_else(|| "ahoy".into(), |stem| stem.to_string_lossy());
+    Ok(program.with_file_name(format!(
+        "{stem}-{year:04}{month:02}{day:02}-{:02}{:02}{:02}.{extension}",
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+    )))
+}
+
+// Howard Hinnant's days-to-date conversion for the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+fn save_screenshot(ahoy: &Ahoy, args: &RunArgs, palette: &Palette) -> anyhow::Result<PathBuf> {
+    let path = timestamped_path(
+        &args.program,
+        SystemTime::now(),
+        ImageFormat::Png.extension(),
+    )?;
+    let mut writer = BufWriter::new(File::create(&path)?);
+    write_image(
+        &ahoy.current_frame,
+        ImageFormat::Png,
+        args.screenshot_scale,
+        palette,
+        &mut writer,
+    )?;
+    writer.flush()?;
+    Ok(path)
+}
+
+fn start_capture(
+    path: PathBuf,
+    args: &RunArgs,
+    palette: Palette,
+) -> anyhow::Result<(PathBuf, AnimationRecorder)> {
+    let format = AnimationFormat::from_path(&path)?;
+    let recorder = AnimationRecorder::new(format, args.capture_scale, palette);
+    Ok((path, recorder))
+}
+
+fn finish_capture((path, recorder): (PathBuf, AnimationRecorder)) -> anyhow::Result<PathBuf> {
+    let mut writer = BufWriter::new(File::create(&path)?);
+    recorder.finish(&mut writer)?;
+    writer.flush()?;
+    Ok(path)
+}
+
+fn load_program(rom: &[u8], args: &RunArgs, settings: &Settings) -> anyhow::Result<Ahoy> {
+    let mut ahoy = Ahoy::default();
+    if args.sanitize {
+        ahoy.enable_sanitizer();
+    }
+    ahoy.load(&mut &rom[..])?;
+    ahoy.quirks = settings.quirks;
+    Ok(ahoy)
+}
 
+fn run_debugger(args: &DebugArgs) -> anyhow::Result<()> {
+    let rom = std::fs::read(&args.program)?;
+    let info = ahoy::database::lookup(&sha1_hex(&rom));
     let mut ahoy = Ahoy::default();
-    ahoy.load(&mut reader)?;
+    ahoy.load(&mut &rom[..])?;
+    ahoy.quirks = match (args.platform, &info) {
+        (Some(platform), _) => platform.into(),
+        (None, Some(info)) => info.quirks().unwrap_or_default(),
+        (None, None) => inspect(&rom).recommend().quirks,
+    };
+    let instructions_per_frame = args
+        .instructions_per_frame
+        .or(info.and_then(|info| info.tickrate))
+        .unwrap_or(INSTRUCTIONS_PER_FRAME);
+    let mut debugger = Debugger::new(
+        ahoy,
+        instructions_per_frame,
+        args.checkpoint_interval,
+        mebibytes(args.checkpoint_budget)?,
+    )?;
+
+    println!("{}", debugger.summary());
+    let mut stdout = std::io::stdout();
+    for line in std::io::stdin().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match line.parse::<DebugCommand>() {
+            Ok(DebugCommand::Quit) => break,
+            Ok(command) => match debugger.apply(&command) {
+                Ok(output) => println!("{output}"),
+                Err(error) => println!("Error: {error}"),
+            },
+            Err(error) => println!("{error}"),
+        }
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+fn print_info(program: &Path) -> anyhow::Result<()> {
+    let rom = std::fs::read(program)?;
+    let inspection = inspect(&rom);
+    println!("File:        {}", program.display());
+    println!("Size:        {} bytes", inspection.size);
+    println!("SHA-1:       {}", inspection.sha1);
+    println!("CRC-32:      {:08x}", inspection.crc32);
+
+    match ahoy::database::lookup(&inspection.sha1) {
+        Some(info) => print_database_info(&info),
+        None => println!("Not in the ROM database"),
+    }
+    print_inspection(&inspection);
+    Ok(())
+}
+
+fn print_database_info(info: &RomInfo) {
+    println!("Title:       {}", info.title);
+    if !info.authors.is_empty() {
+        println!("Authors:     {}", info.authors.join(", "));
+    }
+    if let Some(release) = &info.release {
+        println!("Release:     {release}");
+    }
+    if let Some(description) = &info.description {
+        println!("Description: {description}");
+    }
+    println!("Platforms:   {}", info.platforms.join(", "));
+    if let Some(quirks) = info.quirks() {
+        println!("Quirks:      {quirks}");
+    }
+    if let Some(tickrate) = info.tickrate {
+        println!("Tick rate:   {tickrate} instructions per frame");
+    }
+    if !info.colors.is_empty() {
+        println!("Colours:     {}", info.colors.join(", "));
+    }
+    if !info.keys.is_empty() {
+        let keys: Vec<String> = info
+            .keys
+            .iter()
+            .map(|(action, key)| format!("{action}={key:X}"))
+            .collect();
+        println!("Keys:        {}", keys.join(", "));
+    }
+}
+
+fn print_inspection(inspection: &Inspection) {
+    println!("Detected:    {} (from opcodes)", inspection.platform.name());
+    println!("Reachable:   {} instructions", inspection.reachable.len());
+    if inspection.indirect_jumps {
+        println!("             BNNN jumps hide their targets, so some code may be missing");
+    }
+
+    println!();
+    println!("Instruction families:");
+    for (family, patterns) in &inspection.families {
+        let count: usize = patterns.values().sum();
+        let patterns: Vec<String> = patterns
+            .iter()
+            .map(|(pattern, uses)| format!("{pattern} ({uses})"))
+            .collect();
+        println!("  {family:<16} {count:>4}  {}", patterns.join(", "));
+    }
+
+    if !inspection.unsupported.is_empty() {
+        println!();
+        println!("Not supported by ahoy yet:");
+        for (pattern, addresses) in &inspection.unsupported {
+            println!("  {pattern}  {}", format_addresses(addresses));
+        }
+    }
+
+    let recommendation = inspection.recommend();
+    println!();
+    println!(
+        "Recommended: {} preset with {}",
+        recommendation.platform.name(),
+        recommendation.quirks
+    );
+    for reason in &recommendation.reasons {
+        println!("  {reason}");
+    }
+    for note in &recommendation.notes {
+        println!("  note: {note}");
+    }
+
+    if !inspection.quirk_patterns.is_empty() {
+        println!();
+        println!("Quirk-sensitive:");
+        for found in &inspection.quirk_patterns {
+            println!(
+                "  {}  {:<16} {}: {}",
+                found.pattern,
+                found.quirk,
+                found.effect,
+                format_addresses(&found.addresses)
+            );
+        }
+    }
+}
+
+// Keeps lines short for ROMs that use an opcode everywhere
+fn format_addresses(addresses: &[usize]) -> String {
+    const SHOWN: usize = 6;
+    let mut text: Vec<String> = addresses
+        .iter()
+        .take(SHOWN)
+        .map(|addr| format!("{addr:03X}"))
+        .collect();
+    if addresses.len() > SHOWN {
+        text.push(format!("and {} more", addresses.len() - SHOWN));
+    }
+    text.join(", ")
+}
+
+fn print_sanitizer_reports(ahoy: &Ahoy) {
+    for report in ahoy.sanitizer_reports() {
+        eprintln!("sanitizer: {report}");
+    }
+}
+
+fn run_headless(
+    mut ahoy: Ahoy,
+    args: &RunArgs,
+    settings: &Settings,
+    replay: Option<&Movie>,
+) -> anyhow::Result<()> {
+    // Without a movie the default seed keeps headless runs reproducible
+    let (frames, instructions_per_frame) = match replay {
+        Some(movie) => {
+            movie.start(&mut ahoy);
+            (
+                args.frames.unwrap_or(movie.frames),
+                movie.instructions_per_frame,
+            )
+        }
+        None => (
+            args.frames
+                .ok_or_else(|| anyhow!("Headless runs need --frames or --replay"))?,
+            settings.instructions_per_frame,
+        ),
+    };
 
-    let mut display = RatatuiAhoyDisplay::default();
-    loop {
-        ahoy.process()?;
-        display.draw(&ahoy.current_frame)?;
-        if event::poll(Duration::from_millis(2))? && matches!(event::read()?, Event::Key(_)) {
-            break;
+    let mut capture = match &args.capture {
+        Some(path) => Some(start_capture(path.clone(), args, settings.palette)?),
+        None => None,
+    };
+    let result: anyhow::Result<()> = (0..frames).try_for_each(|frame| {
+        if let Some(movie) = replay {
+            movie.apply_frame(&mut ahoy, frame);
+        }
+        ahoy.run_frame(instructions_per_frame)?;
+        if let Some((_, recorder)) = &mut capture {
+            recorder.record(&ahoy.current_frame);
         }
+        Ok(())
+    });
+    print_sanitizer_reports(&ahoy);
+    // Written even when the run fails, as that is when it's most useful
+    if let Some(capture) = capture
+        && let Err(error) = finish_capture(capture)
+    {
+        eprintln!("Could not write the capture: {error}");
+    }
+    result?;
+
+    let hash = frame_hash(&ahoy.current_frame);
+    match args.output.as_deref() {
+        Some(path) if path == Path::new("-") => {
+            let mut stdout = std::io::stdout().lock();
+            dump_frame(&ahoy, args, &settings.palette, &mut stdout)?;
+            stdout.flush()?;
+            eprintln!("{hash:016X}");
+        }
+        Some(path) => {
+            let mut writer = BufWriter::new(File::create(path)?);
+            dump_frame(&ahoy, args, &settings.palette, &mut writer)?;
+            writer.flush()?;
+            println!("{hash:016X}");
+        }
+        None => println!("{hash:016X}"),
+    }
+
+    if let Some(expected) = args.expect_hash
+        && expected != hash
+    {
+        return Err(anyhow!(
+            "Final frame hash {:016X} does not match the expected {:016X}",
+            hash,
+            expected
+        ));
     }
-    ratatui::restore();
     Ok(())
 }
+
+fn main() -> anyhow::Result<()> {
+    init_cli_log!();
+
+    let args = match Cli::parse().command {
+        Command::Run(args) => args,
+        Command::Info(args) => return print_info(&args.program),
+        Command::Debug(args) => return run_debugger(&args),
+    };
+    let rom = std::fs::read(&args.program)?;
+    let info = ahoy::database::lookup(&sha1_hex(&rom));
+    let settings = Settings::resolve(&args, &rom, info.as_ref())?;
+    let mut ahoy = load_program(&rom, &args, &settings)?;
+
+    let replay = match &args.replay {
+        Some(path) => {
+            let movie = Movie::read(&mut BufReader::new(File::open(path)?))?;
+            movie.check_rom(&rom)?;
+            Some(movie)
+        }
+        None => None,
+    };
+    if args.headless {
+        return run_headless(ahoy, &args, &settings, replay.as_ref());
+    }
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64;
+    let instructions_per_frame = match &replay {
+        Some(movie) => {
+            movie.start(&mut ahoy);
+            movie.instructions_per_frame
+        }
+        None => {
+            ahoy.seed_rng(seed);
+            settings.instructions_per_frame
+        }
+    };
+    let mut recording = args
+        .record
+        .as_ref()
+        .map(|_| Movie::new(&rom, seed, ahoy.quirks, instructions_per_frame));
+
+    let mut rewind = Rewind::new(args.rewind_interval, mebibytes(args.rewind_budget)?);
+    let mut rewind_key = HeldKey::default();
+    let mut keypad = [HeldKey::default(); 16];
+    let mut frame = 0_u64;
+
+    let mut filter = FrameFilter::new(settings.filter);
+    let rom_name = match &info {
+        Some(info) => info.title.clone(),
+        None => args.program.file_name().map_or_else(
+            || args.program.display().to_string(),
+            |name| name.to_string_lossy().into(),
+        ),
+    };
+    let protocol = match (args.display, &args.cast) {
+        (DisplayChoice::Auto, Some(_)) => None,
+        (DisplayChoice::Sixel | DisplayChoice::Kitty, Some(_)) => {
+            return Err(anyhow!("Casts can only record the text display"));
+        }
+        (choice, _) => choice.protocol(),
+    };
+    let mut display: Box<dyn AhoyDisplay> = match protocol {
+        Some(protocol) => Box::new(GraphicsAhoyDisplay::terminal(protocol, settings.palette)?),
+        None => {
+            let output: Box<dyn Write> = match &args.cast {
+                Some(path) => {
+                    let (width, height) = crossterm::terminal::size()?;
+                    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                    let writer = BufWriter::new(File::create(path)?);
+                    let cast = CastWriter::new(writer, width, height, timestamp, &rom_name)?;
+                    Box::new(CastTee::new(std::io::stdout(), cast))
+                }
+                None => Box::new(std::io::stdout()),
+            };
+            Box::new(RatatuiAhoyDisplay::new(
+                output,
+                settings.renderer,
+                settings.palette,
+                &rom_name,
+            )?)
+        }
+    };
+    let keyboard = KeyboardEnhancement::enable()?;
+    let (key_hold, rewind_hold) = match keyboard {
+        Some(_) => (None, None),
+        None => (
+            Some(Duration::from_millis(args.key_hold)),
+            Some(REWIND_HOLD),
+        ),
+    };
+    let mut capture = match &args.capture {
+        Some(path) => Some(start_capture(path.clone(), &args, settings.palette)?),
+        None => None,
+    };
+    let mut controls = Controls::default();
+    controls.keypad = args.keypad;
+    let mut drawn = None;
+    let mut force_redraw = true;
+    let result = (|| -> anyhow::Result<()> {
+        'emulation: loop {
+            let frame_start = Instant::now();
+            if rewind_key.is_held(frame_start) {
+                if let Some(previous) = rewind.rewind()? {
+                    ahoy.restore(previous);
+                    force_redraw = true;
+                }
+            } else {
+                for _ in 0..controls.frames_this_tick() {
+                    match &replay {
+                        Some(movie) if frame < movie.frames => movie.apply_frame(&mut ahoy, frame),
+                        _ => {
+                            let previous = ahoy.keypad();
+                            for (key, held) in keypad.iter().enumerate() {
+                                ahoy.set_key(key as u8, held.is_held(frame_start));
+                            }
+                            if let Some(movie) = &mut recording {
+                                movie.record_keypad(frame, previous, ahoy.keypad());
+                            }
+                        }
+                    }
+                    let instructions = controls.instructions_per_frame(instructions_per_frame);
+                    ahoy.run_frame(instructions)?;
+                    rewind.record(&ahoy)?;
+                    frame += 1;
+                }
+            }
+            if let Some((_, recorder)) = &mut capture {
+                recorder.record(&ahoy.current_frame);
+            }
+            let status = controls.status(match (&replay, &recording) {
+                _ if rewind_key.is_held(frame_start) => "rewinding",
+                (Some(movie), _) if frame < movie.frames => "replaying",
+                (_, Some(_)) => "recording",
+                _ if capture.is_some() => "capturing",
+                _ => "running",
+            });
+            // Terminal output is the bottleneck, so only redraw when the picture changes
+            let keypad_panel = controls
+                .keypad
+                .then(|| keypad_view(&ahoy, &settings.keymap));
+            let shown = Some((ahoy.frame_generation(), status, keypad_panel));
+            if force_redraw || shown != drawn || filter.is_fading() {
+                if let Some((_, status, keypad_panel)) = &shown {
+                    display.set_status(status);
+                    display.set_keypad(keypad_panel.clone());
+                }
+                display.set_overlay(&controls.overlay_lines());
+                display.draw_glow(filter.apply(&ahoy.current_frame))?;
+                drawn = shown;
+                force_redraw = false;
+            }
+
+            while let Some(remaining) = FRAME_DURATION.checked_sub(frame_start.elapsed()) {
+                if !event::poll(remaining)? {
+                    break;
+                }
+                let key = match event::read()? {
+                    Event::Key(key) => key,
+                    Event::Resize(..) => {
+                        force_redraw = true;
+                        continue;
+                    }
+                    _ => continue,
+                };
+                let chip8_key = keypad_key(key.code, &settings.keymap);
+                match key.kind {
+                    KeyEventKind::Release => {
+                        match (chip8_key, key.code) {
+                            (Some(chip8_key), _) => keypad[chip8_key as usize].release(),
+                            (None, KeyCode::Backspace) => rewind_key.release(),
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    // Only held keys care about repeats, hotkeys fire once per press
+                    KeyEventKind::Repeat
+                        if chip8_key.is_none() && key.code != KeyCode::Backspace =>
+                    {
+                        continue;
+                    }
+                    _ => {}
+                }
+                if let Some(control) = Control::for_key(key) {
+                    match control {
+                        Control::Quit => break 'emulation,
+                        Control::Pause => controls.toggle_pause(),
+                        Control::Advance => controls.advance(),
+                        // The movie stores a single rate, so it could not replay a change
+                        Control::Faster | Control::Slower | Control::Reset
+                            if recording.is_some() || replay.is_some() =>
+                        {
+                            warn!(
+                                "Speed changes and resets are disabled while recording or replaying"
+                            );
+                        }
+                        Control::Faster => controls.faster(),
+                        Control::Slower => controls.slower(),
+                        Control::Turbo => controls.turbo = !controls.turbo,
+                        Control::Reset => match load_program(&rom, &args, &settings) {
+                            Ok(reloaded) => {
+                                ahoy = reloaded;
+                                ahoy.seed_rng(
+                                    SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64,
+                                );
+                                rewind.clear();
+                                filter.clear();
+                                info!("Reset {}", rom_name);
+                            }
+                            Err(error) => warn!("Could not reload {}: {}", rom_name, error),
+                        },
+                        Control::Overlay => controls.overlay = !controls.overlay,
+                        Control::Keypad => controls.keypad = !controls.keypad,
+                    }
+                    force_redraw = true;
+                    continue;
+                }
+                if let Some(chip8_key) = chip8_key {
+                    keypad[chip8_key as usize].press(Instant::now(), key_hold);
+                    continue;
+                }
+                match key.code {
+                    // Movie events are keyed by frame, which a restored state would skip back past
+                    KeyCode::Backspace | KeyCode::F(5..=8)
+                        if recording.is_some() || replay.is_some() =>
+                    {
+                        warn!(
+                            "Rewinding and loading states are disabled while recording or replaying"
+                        );
+                    }
+                    KeyCode::Backspace => rewind_key.press(Instant::now(), rewind_hold),
+                    KeyCode::F(11) => match capture.take() {
+                        Some(finished) => match finish_capture(finished) {
+                            Ok(path) => info!("Saved capture to {}", path.display()),
+                            Err(error) => warn!("Could not save capture: {}", error),
+                        },
+                        None => {
+                            let extension = AnimationFormat::Gif.extension();
+                            match timestamped_path(&args.program, SystemTime::now(), extension)
+                                .and_then(|path| start_capture(path, &args, settings.palette))
+                            {
+                                Ok(started) => capture = Some(started),
+                                Err(error) => warn!("Could not start capture: {}", error),
+                            }
+                        }
+                    },
+                    KeyCode::F(12) => match save_screenshot(&ahoy, &args, &settings.palette) {
+                        Ok(path) => info!("Saved screenshot to {}", path.display()),
+                        Err(error) => warn!("Could not save screenshot: {}", error),
+                    },
+                    // F1-F4 save into slots 1-4, F5-F8 load them back
+                    KeyCode::F(n @ 1..=SAVE_SLOTS) => match save_slot(&ahoy, &args.program, n) {
+                        Ok(()) => info!("Saved state to slot {}", n),
+                        Err(error) => warn!("Could not save slot {}: {}", n, error),
+                    },
+                    KeyCode::F(n) if (SAVE_SLOTS + 1..=SAVE_SLOTS * 2).contains(&n) => {
+                        let slot = n - SAVE_SLOTS;
+                        match load_slot(&args.program, slot) {
+                            Ok(loaded) => {
+                                ahoy.restore(loaded);
+                                rewind.clear();
+                                filter.clear();
+                                force_redraw = true;
+                                info!("Loaded state from slot {}", slot);
+                            }
+                            Err(error) => warn!("Could not load slot {}: {}", slot, error),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    })();
+    drop(keyboard);
+    drop(display);
+    print_sanitizer_reports(&ahoy);
+    // Written even when the run fails, as that is when they're most useful
+    let captured = capture.map(finish_capture).transpose();
+    if let Ok(Some(path)) = &captured {
+        eprintln!("Saved capture to {}", path.display());
+    }
+    let recorded = match (&args.record, recording) {
+        (Some(path), Some(mut movie)) => {
+            movie.frames = frame;
+            save_movie(&movie, path)
+        }
+        _ => Ok(()),
+    };
+    result?;
+    captured?;
+    recorded
+}
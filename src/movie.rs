@@ -0,0 +1,288 @@
+use std::io::{BufRead, Write};
+
+use anyhow::anyhow;
+
+use crate::{Ahoy, database::sha1_hex, quirks::Quirks};
+
+const MOVIE_HEADER: &str = "ahoy-movie 2";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MovieEvent {
+    pub frame: u64,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Movie {
+    pub rom_sha1: String,
+    pub seed: u64,
+    pub quirks: Quirks,
+    pub instructions_per_frame: usize,
+    pub frames: u64,
+    pub events: Vec<MovieEvent>,
+}
+
+impl Movie {
+    pub fn new(rom: &[u8], seed: u64, quirks: Quirks, instructions_per_frame: usize) -> Self {
+        Self {
+            rom_sha1: sha1_hex(rom),
+            seed,
+            quirks,
+            instructions_per_frame,
+            frames: 0,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn record_keypad(&mut self, frame: u64, previous: u16, current: u16) {
+        for key in 0..16 {
+            let mask = 1 << key;
+            if (previous ^ current) & mask != 0 {
+                self.events.push(MovieEvent {
+                    frame,
+                    key,
+                    pressed: current & mask != 0,
+                });
+            }
+        }
+    }
+
+    // Replaying the inputs against another program would silently diverge
+    pub fn check_rom(&self, rom: &[u8]) -> anyhow::Result<()> {
+        let sha1 = sha1_hex(rom);
+        if sha1 != self.rom_sha1 {
+            return Err(anyhow!(
+                "Movie was recorded with ROM {}, not {}",
+                self.rom_sha1,
+                sha1
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn start(&self, ahoy: &mut Ahoy) {
+        ahoy.seed_rng(self.seed);
+        ahoy.quirks = self.quirks;
+    }
+
+    pub fn apply_frame(&self, ahoy: &mut Ahoy, frame: u64) {
+        // Events are sorted by frame, so this frame's events sit in one run
+        let first = self.events.partition_point(|event| event.frame < frame);
+        for event in self.events[first..]
+            .iter()
+            .take_while(|event| event.frame == frame)
+        {
+            ahoy.set_key(event.key, event.pressed);
+        }
+    }
+
+    pub fn replay(&self, ahoy: &mut Ahoy) -> anyhow::Result<()> {
+        self.start(ahoy);
+        for frame in 0..self.frames {
+            self.apply_frame(ahoy, frame);
+            ahoy.run_frame(self.instructions_per_frame)?;
+        }
+        Ok(())
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        writeln!(writer, "{MOVIE_HEADER}")?;
+        writeln!(writer, "rom {}", self.rom_sha1)?;
+        writeln!(writer, "seed {:016X}", self.seed)?;
+        writeln!(writer, "quirks {:02X}", self.quirks.to_bits())?;
+        writeln!(writer, "ipf {}", self.instructions_per_frame)?;
+        writeln!(writer, "frames {}", self.frames)?;
+        for event in &self.events {
+            let state = if event.pressed { "down" } else { "up" };
+            writeln!(writer, "{} {:X} {}", event.frame, event.key, state)?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: BufRead>(reader: &mut R) -> anyhow::Result<Self> {
+        let mut lines = reader.lines();
+        if lines.next().transpose()?.as_deref() != Some(MOVIE_HEADER) {
+            return Err(anyhow!("Not an Ahoy movie file"));
+        }
+
+        let mut header = |name: &str| -> anyhow::Result<String> {
+            let line = lines.next().transpose()?.unwrap_or_default();
+            line.strip_prefix(name)
+                .and_then(|value| value.strip_prefix(' '))
+                .map(str::to_owned)
+                .ok_or_else(|| anyhow!("Movie is missing its `{}` header", name))
+        };
+        let rom_sha1 = header("rom")?;
+        let seed = u64::from_str_radix(&header("seed")?, 16)?;
+        let quirks = Quirks::from_bits(u8::from_str_radix(&header("quirks")?, 16)?);
+        let instructions_per_frame = header("ipf")?.parse()?;
+        let frames = header("frames")?.parse()?;
+
+        let mut events: Vec<MovieEvent> = Vec::new();
+        for line in lines {
+            let line = line?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [frame, key, state] = fields[..] else {
+                return Err(anyhow!("Malformed movie event: {}", line));
+            };
+            let frame = frame.parse()?;
+            if events.last().is_some_and(|last| last.frame > frame) {
+                return Err(anyhow!("Movie event is out of order: {}", line));
+            }
+            events.push(MovieEvent {
+                frame,
+                key: u8::from_str_radix(key, 16)? & 0xF,
+                pressed: match state {
+                    "down" => true,
+                    "up" => false,
+                    _ => return Err(anyhow!("Malformed movie event: {}", line)),
+                },
+            });
+        }
+
+        Ok(Self {
+            rom_sha1,
+            seed,
+            quirks,
+            instructions_per_frame,
+            frames,
+            events,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        Ahoy,
+        display::frame_hash,
+        movie::{Movie, MovieEvent},
+        quirks::Quirks,
+    };
+
+    // Draws a font sprite at a random spot, forever
+    fn random_drawing_ahoy() -> Ahoy {
+        let mut ahoy = Ahoy::default();
+        ahoy.memory[0x200..0x20A]
+            .copy_from_slice(&[0xA0, 0x50, 0xC0, 0x37, 0xC1, 0x0F, 0xD0, 0x15, 0x12, 0x02]);
+        ahoy
+    }
+
+    #[test]
+    fn record_keypad_only_logs_changed_keys() {
+        let mut movie = Movie::new(&[0x12, 0x00], 1, Quirks::default(), 11);
+
+        movie.record_keypad(3, 0b0000, 0b0101);
+        movie.record_keypad(7, 0b0101, 0b0100);
+
+        assert_eq!(
+            movie.events,
+            vec![
+                MovieEvent {
+                    frame: 3,
+                    key: 0,
+                    pressed: true
+                },
+                MovieEvent {
+                    frame: 3,
+                    key: 2,
+                    pressed: true
+                },
+                MovieEvent {
+                    frame: 7,
+                    key: 0,
+                    pressed: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn movie_survives_a_write_read_round_trip() {
+        let mut movie = Movie::new(
+            &[0x12, 0x00],
+            0xDEADBEEF,
+            Quirks {
+                shifting: true,
+                ..Default::default()
+            },
+            15,
+        );
+        movie.record_keypad(12, 0, 0x8001);
+        movie.frames = 40;
+
+        let mut written = Vec::new();
+        movie.write(&mut written).unwrap();
+
+        assert_eq!(Movie::read(&mut Cursor::new(written)).unwrap(), movie);
+    }
+
+    #[test]
+    fn read_rejects_other_files() {
+        Movie::read(&mut Cursor::new("seed 1\n"))
+            .expect_err("Expected missing header to raise error");
+    }
+
+    #[test]
+    fn read_rejects_events_out_of_order() {
+        let mut movie = Movie::new(&[0x12, 0x00], 1, Quirks::default(), 11);
+        movie.record_keypad(9, 0, 1);
+        movie.record_keypad(4, 1, 0);
+
+        let mut written = Vec::new();
+        movie.write(&mut written).unwrap();
+
+        Movie::read(&mut Cursor::new(written))
+            .expect_err("Expected out of order events to raise error");
+    }
+
+    #[test]
+    fn apply_frame_only_presses_that_frames_keys() {
+        let mut movie = Movie::new(&[0x12, 0x00], 1, Quirks::default(), 11);
+        movie.record_keypad(2, 0b000, 0b001);
+        movie.record_keypad(5, 0b001, 0b011);
+        movie.record_keypad(5, 0b011, 0b111);
+        let mut ahoy = Ahoy::default();
+
+        movie.apply_frame(&mut ahoy, 5);
+
+        assert_eq!(ahoy.keypad(), 0b110);
+    }
+
+    #[test]
+    fn check_rom_rejects_another_program() {
+        let movie = Movie::new(&[0x12, 0x00], 1, Quirks::default(), 11);
+
+        movie.check_rom(&[0x12, 0x00]).unwrap();
+        movie
+            .check_rom(&[0x13, 0x00])
+            .expect_err("Expected a different ROM to raise error");
+    }
+
+    #[test]
+    fn replay_reproduces_a_recorded_run() {
+        let mut movie = Movie::new(&[0x12, 0x02], 0x5EED, Quirks::default(), 11);
+        let mut recorded = random_drawing_ahoy();
+        movie.start(&mut recorded);
+        for frame in 0..120 {
+            let previous = recorded.keypad();
+            recorded.set_key((frame % 16) as u8, frame % 3 == 0);
+            movie.record_keypad(frame, previous, recorded.keypad());
+            recorded.run_frame(movie.instructions_per_frame).unwrap();
+        }
+        movie.frames = 120;
+
+        let mut replayed = random_drawing_ahoy();
+        movie.replay(&mut replayed).unwrap();
+
+        assert_eq!(
+            frame_hash(&replayed.current_frame),
+            frame_hash(&recorded.current_frame)
+        );
+        assert_eq!(replayed.keypad(), recorded.keypad());
+        assert_eq!(replayed.rng_state, recorded.rng_state);
+    }
+}
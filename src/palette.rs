@@ -0,0 +1,286 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use ratatui::style::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl FromStr for Rgb {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let hex = value.trim_start_matches('#');
+        if hex.len() != 6 {
+            return Err(anyhow!("Expected a colour like #RRGGBB, got {:?}", value));
+        }
+        let rgb = u32::from_str_radix(hex, 16)
+            .map_err(|_| anyhow!("Expected a colour like #RRGGBB, got {:?}", value))?;
+        Ok(Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+    }
+}
+
+impl Rgb {
+    fn distance(self, other: Rgb) -> u32 {
+        [(self.0, other.0), (self.1, other.1), (self.2, other.2)]
+            .iter()
+            .map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32)
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorDepth {
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if matches!(colorterm.as_str(), "truecolor" | "24bit") {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+// Background, first plane, second plane, and both planes overlapping; plain
+// CHIP-8 only ever uses the first two
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [Rgb; 4],
+}
+
+impl Palette {
+    pub const NAMES: [&str; 5] = ["classic", "amber", "green", "octo", "high-contrast"];
+
+    pub fn named(name: &str) -> Option<Self> {
+        let colors = match name {
+            "classic" => ["#000000", "#FFFFFF", "#AAAAAA", "#555555"],
+            "amber" => ["#1A0F00", "#FFB000", "#B37A00", "#664400"],
+            "green" => ["#0A1A0A", "#33FF33", "#22AA22", "#115511"],
+            // Octo's defaults, so screenshots match the reference IDE
+            "octo" => ["#996600", "#FFCC00", "#FF6600", "#662200"],
+            "high-contrast" => ["#000000", "#FFFFFF", "#FFFF00", "#00FFFF"],
+            _ => return None,
+        };
+        Some(Self {
+            colors: colors.map(|color| color.parse().unwrap_or(Rgb(0, 0, 0))),
+        })
+    }
+
+    pub fn background(&self) -> Rgb {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> Rgb {
+        self.colors[1]
+    }
+
+    // Blends from the background at 0 to the foreground at 255
+    pub fn shade(&self, brightness: u8, depth: ColorDepth) -> Color {
+        let mix = |background: u8, foreground: u8| {
+            let (background, foreground) = (background as u32, foreground as u32);
+            ((background * (255 - brightness as u32) + foreground * brightness as u32) / 255) as u8
+        };
+        let (background, foreground) = (self.background(), self.foreground());
+        terminal_color(
+            Rgb(
+                mix(background.0, foreground.0),
+                mix(background.1, foreground.1),
+                mix(background.2, foreground.2),
+            ),
+            depth,
+        )
+    }
+
+    pub fn color(&self, index: usize, depth: ColorDepth) -> Color {
+        terminal_color(self.colors[index % self.colors.len()], depth)
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::named("classic").unwrap_or(Self {
+            colors: [
+                Rgb(0, 0, 0),
+                Rgb(255, 255, 255),
+                Rgb(170, 170, 170),
+                Rgb(85, 85, 85),
+            ],
+        })
+    }
+}
+
+// Either a palette name or 2-4 comma separated hex colours. Missing plane
+// colours fall back to the foreground
+impl FromStr for Palette {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(palette) = Self::named(value) {
+            return Ok(palette);
+        }
+        if !value.contains(',') {
+            return Err(anyhow!(
+                "Unknown palette {:?}, expected one of {} or a list of hex colours",
+                value,
+                Self::NAMES.join(", ")
+            ));
+        }
+
+        let colors = value
+            .split(',')
+            .map(|color| color.trim().parse())
+            .collect::<anyhow::Result<Vec<Rgb>>>()?;
+        if !(2..=4).contains(&colors.len()) {
+            return Err(anyhow!(
+                "Expected 2 to 4 colours in a palette, got {}",
+                colors.len()
+            ));
+        }
+        let mut palette = [colors[1]; 4];
+        palette[..colors.len()].copy_from_slice(&colors);
+        Ok(Self { colors: palette })
+    }
+}
+
+fn terminal_color(rgb: Rgb, depth: ColorDepth) -> Color {
+    match depth {
+        ColorDepth::TrueColor => Color::Rgb(rgb.0, rgb.1, rgb.2),
+        ColorDepth::Ansi256 => Color::Indexed(nearest_ansi256(rgb)),
+        ColorDepth::Ansi16 => nearest_ansi16(rgb),
+    }
+}
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn nearest_ansi256(rgb: Rgb) -> u8 {
+    let level = |channel: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|i| (CUBE_LEVELS[*i] as i32 - channel as i32).abs())
+            .unwrap_or(0)
+    };
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = Rgb(CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]);
+
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray_step = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_level = 8 + 10 * gray_step;
+    let gray = Rgb(gray_level, gray_level, gray_level);
+
+    if rgb.distance(gray) < rgb.distance(cube) {
+        232 + gray_step
+    } else {
+        16 + 36 * r as u8 + 6 * g as u8 + b as u8
+    }
+}
+
+fn nearest_ansi16(rgb: Rgb) -> Color {
+    // The xterm defaults, which most terminal themes stay close to
+    const ANSI16: [(Color, Rgb); 16] = [
+        (Color::Black, Rgb(0, 0, 0)),
+        (Color::Red, Rgb(205, 0, 0)),
+        (Color::Green, Rgb(0, 205, 0)),
+        (Color::Yellow, Rgb(205, 205, 0)),
+        (Color::Blue, Rgb(0, 0, 238)),
+        (Color::Magenta, Rgb(205, 0, 205)),
+        (Color::Cyan, Rgb(0, 205, 205)),
+        (Color::Gray, Rgb(229, 229, 229)),
+        (Color::DarkGray, Rgb(127, 127, 127)),
+        (Color::LightRed, Rgb(255, 0, 0)),
+        (Color::LightGreen, Rgb(0, 255, 0)),
+        (Color::LightYellow, Rgb(255, 255, 0)),
+        (Color::LightBlue, Rgb(92, 92, 255)),
+        (Color::LightMagenta, Rgb(255, 0, 255)),
+        (Color::LightCyan, Rgb(0, 255, 255)),
+        (Color::White, Rgb(255, 255, 255)),
+    ];
+    ANSI16
+        .iter()
+        .min_by_key(|(_, candidate)| rgb.distance(*candidate))
+        .map_or(Color::White, |(color, _)| *color)
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::Color;
+
+    use crate::palette::{ColorDepth, Palette, Rgb};
+
+    #[test]
+    fn parses_hex_colours_with_or_without_hash() {
+        assert_eq!("#FF8000".parse::<Rgb>().unwrap(), Rgb(255, 128, 0));
+        assert_eq!("0a0b0c".parse::<Rgb>().unwrap(), Rgb(10, 11, 12));
+        assert!("#FFF".parse::<Rgb>().is_err());
+        assert!("#GGGGGG".parse::<Rgb>().is_err());
+    }
+
+    #[test]
+    fn every_named_palette_exists() {
+        for name in Palette::NAMES {
+            assert!(Palette::named(name).is_some(), "{name} is missing");
+        }
+        assert_eq!(
+            "octo".parse::<Palette>().unwrap().foreground(),
+            Rgb(0xFF, 0xCC, 0x00)
+        );
+    }
+
+    #[test]
+    fn custom_palettes_fill_missing_planes_with_the_foreground() {
+        let palette: Palette = "#101010,#F0F0F0".parse().unwrap();
+
+        assert_eq!(
+            palette.colors,
+            [
+                Rgb(16, 16, 16),
+                Rgb(240, 240, 240),
+                Rgb(240, 240, 240),
+                Rgb(240, 240, 240)
+            ]
+        );
+        assert!("sepia".parse::<Palette>().is_err());
+        assert!("#000000".parse::<Palette>().is_err());
+        assert!(
+            "#000000,#111111,#222222,#333333,#444444"
+                .parse::<Palette>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn colours_degrade_to_the_terminal_depth() {
+        let palette: Palette = "#000000,#FFB000,#808080".parse().unwrap();
+
+        assert_eq!(
+            palette.color(1, ColorDepth::TrueColor),
+            Color::Rgb(255, 176, 0)
+        );
+        assert_eq!(palette.color(1, ColorDepth::Ansi256), Color::Indexed(214));
+        assert_eq!(palette.color(2, ColorDepth::Ansi256), Color::Indexed(244));
+        assert_eq!(palette.color(0, ColorDepth::Ansi16), Color::Black);
+        assert_eq!(palette.color(1, ColorDepth::Ansi16), Color::Yellow);
+    }
+
+    #[test]
+    fn shades_blend_background_into_foreground() {
+        let palette: Palette = "#000000,#FF8040".parse().unwrap();
+
+        assert_eq!(palette.shade(0, ColorDepth::TrueColor), Color::Rgb(0, 0, 0));
+        assert_eq!(
+            palette.shade(128, ColorDepth::TrueColor),
+            Color::Rgb(128, 64, 32)
+        );
+        assert_eq!(
+            palette.shade(255, ColorDepth::TrueColor),
+            Color::Rgb(255, 128, 64)
+        );
+    }
+}
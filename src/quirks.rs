@@ -0,0 +1,154 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::anyhow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quirks {
+    pub vf_reset: bool,
+    pub memory_increment: bool,
+    pub display_wait: bool,
+    pub clipping: bool,
+    pub shifting: bool,
+    pub jumping: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl Platform {
+    pub const ALL: [Platform; 3] = [Platform::Chip8, Platform::SuperChip, Platform::XoChip];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::Chip8 => "chip8",
+            Platform::SuperChip => "schip",
+            Platform::XoChip => "xochip",
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Platform::ALL
+            .into_iter()
+            .find(|platform| platform.name() == value)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown platform {:?}, expected chip8, schip or xochip",
+                    value
+                )
+            })
+    }
+}
+
+impl From<Platform> for Quirks {
+    fn from(platform: Platform) -> Self {
+        match platform {
+            Platform::Chip8 => Quirks {
+                vf_reset: true,
+                memory_increment: true,
+                display_wait: true,
+                clipping: true,
+                shifting: false,
+                jumping: false,
+            },
+            Platform::SuperChip => Quirks {
+                vf_reset: false,
+                memory_increment: false,
+                display_wait: false,
+                clipping: true,
+                shifting: true,
+                jumping: true,
+            },
+            Platform::XoChip => Quirks {
+                vf_reset: false,
+                memory_increment: true,
+                display_wait: false,
+                clipping: false,
+                shifting: false,
+                jumping: false,
+            },
+        }
+    }
+}
+
+impl Quirks {
+    // In save state bit order, named as in the config file
+    fn flags(self) -> [(&'static str, bool); 6] {
+        [
+            ("vf-reset", self.vf_reset),
+            ("memory-increment", self.memory_increment),
+            ("display-wait", self.display_wait),
+            ("clipping", self.clipping),
+            ("shifting", self.shifting),
+            ("jumping", self.jumping),
+        ]
+    }
+
+    pub fn to_bits(self) -> u8 {
+        self.flags()
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (bit, (_, enabled))| {
+                bits | ((*enabled as u8) << bit)
+            })
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            vf_reset: bits & 0b000001 != 0,
+            memory_increment: bits & 0b000010 != 0,
+            display_wait: bits & 0b000100 != 0,
+            clipping: bits & 0b001000 != 0,
+            shifting: bits & 0b010000 != 0,
+            jumping: bits & 0b100000 != 0,
+        }
+    }
+}
+
+// The enabled quirks, e.g. "clipping, shifting, jumping"
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let enabled: Vec<&str> = self
+            .flags()
+            .into_iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| name)
+            .collect();
+        if enabled.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", enabled.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::quirks::{Platform, Quirks};
+
+    #[test]
+    fn display_lists_the_enabled_quirks() {
+        assert_eq!(
+            Quirks::from(Platform::SuperChip).to_string(),
+            "clipping, shifting, jumping"
+        );
+        assert_eq!(
+            Quirks::from(Platform::Chip8).to_string(),
+            "vf-reset, memory-increment, display-wait, clipping"
+        );
+        assert_eq!(Quirks::default().to_string(), "none");
+    }
+
+    #[test]
+    fn bits_round_trip() {
+        let quirks = Quirks::from(Platform::Chip8);
+        assert_eq!(Quirks::from_bits(quirks.to_bits()), quirks);
+    }
+}
@@ -0,0 +1,212 @@
+use std::{collections::VecDeque, io::Cursor};
+
+use crate::Ahoy;
+
+pub struct Rewind {
+    interval: usize,
+    budget: usize,
+    frames_since_snapshot: usize,
+    latest: Option<Vec<u8>>,
+    // Each delta turns a snapshot back into the one taken before it
+    deltas: VecDeque<Vec<u8>>,
+    deltas_size: usize,
+}
+
+impl Rewind {
+    pub fn new(interval: usize, budget: usize) -> Self {
+        Self {
+            interval: interval.max(1),
+            budget,
+            frames_since_snapshot: 0,
+            latest: None,
+            deltas: VecDeque::new(),
+            deltas_size: 0,
+        }
+    }
+
+    pub fn record(&mut self, ahoy: &Ahoy) -> anyhow::Result<()> {
+        self.frames_since_snapshot += 1;
+        if self.frames_since_snapshot < self.interval {
+            return Ok(());
+        }
+        self.frames_since_snapshot = 0;
+
+        let mut snapshot = Vec::new();
+        ahoy.save_state(&mut snapshot)?;
+
+        if let Some(latest) = self.latest.take() {
+            let delta = encode_delta(&snapshot, &latest);
+            self.deltas_size += delta.len();
+            self.deltas.push_back(delta);
+        }
+        self.latest = Some(snapshot);
+
+        while self.used_bytes() > self.budget {
+            match self.deltas.pop_front() {
+                Some(oldest) => self.deltas_size -= oldest.len(),
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn rewind(&mut self) -> anyhow::Result<Option<Ahoy>> {
+        let Some(latest) = self.latest.take() else {
+            return Ok(None);
+        };
+        let ahoy = Ahoy::load_state(&mut Cursor::new(&latest))?;
+
+        self.latest = Some(match self.deltas.pop_back() {
+            Some(delta) => {
+                self.deltas_size -= delta.len();
+                apply_delta(&latest, &delta)
+            }
+            None => latest,
+        });
+        self.frames_since_snapshot = 0;
+
+        Ok(Some(ahoy))
+    }
+
+    pub fn clear(&mut self) {
+        self.frames_since_snapshot = 0;
+        self.latest = None;
+        self.deltas.clear();
+        self.deltas_size = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.latest.as_ref().map_or(0, |_| self.deltas.len() + 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.latest.as_ref().map_or(0, Vec::len) + self.deltas_size
+    }
+}
+
+// A delta is the target length followed by (skip, length, bytes) runs
+// holding the non-zero stretches of `from ^ to`.
+pub(crate) fn encode_delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+    let xored: Vec<u8> = (0..to.len())
+        .map(|i| from.get(i).copied().unwrap_or(0) ^ to[i])
+        .collect();
+
+    let mut delta = (to.len() as u32).to_le_bytes().to_vec();
+    let mut position = 0;
+    while position < xored.len() {
+        let skip = xored[position..]
+            .iter()
+            .take(u16::MAX as usize)
+            .take_while(|byte| **byte == 0)
+            .count();
+        let start = position + skip;
+        let length = xored[start..]
+            .iter()
+            .take(u16::MAX as usize)
+            .take_while(|byte| **byte != 0)
+            .count();
+
+        delta.extend((skip as u16).to_le_bytes());
+        delta.extend((length as u16).to_le_bytes());
+        delta.extend(&xored[start..start + length]);
+        position = start + length;
+    }
+    delta
+}
+
+pub(crate) fn apply_delta(from: &[u8], delta: &[u8]) -> Vec<u8> {
+    let target_len = u32::from_le_bytes([delta[0], delta[1], delta[2], delta[3]]) as usize;
+    let mut to = from.to_vec();
+    to.resize(target_len, 0);
+
+    let mut position = 0;
+    let mut cursor = 4;
+    while cursor < delta.len() {
+        let skip = u16::from_le_bytes([delta[cursor], delta[cursor + 1]]) as usize;
+        let length = u16::from_le_bytes([delta[cursor + 2], delta[cursor + 3]]) as usize;
+        cursor += 4;
+        position += skip;
+        for (byte, change) in to[position..position + length]
+            .iter_mut()
+            .zip(&delta[cursor..cursor + length])
+        {
+            *byte ^= change;
+        }
+        position += length;
+        cursor += length;
+    }
+    to
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Ahoy,
+        rewind::{Rewind, apply_delta, encode_delta},
+    };
+
+    #[test]
+    fn delta_round_trips_between_different_lengths() {
+        let from = vec![1_u8, 2, 3, 4, 5, 6, 7, 8];
+        let to = vec![1_u8, 2, 9, 4, 5, 6, 0, 8, 10, 11];
+
+        assert_eq!(apply_delta(&from, &encode_delta(&from, &to)), to);
+        assert_eq!(apply_delta(&to, &encode_delta(&to, &from)), from);
+    }
+
+    #[test]
+    fn delta_of_identical_states_is_compact() {
+        let mut state = Vec::new();
+        Ahoy::default().save_state(&mut state).unwrap();
+
+        assert!(encode_delta(&state, &state).len() < 16);
+    }
+
+    #[test]
+    fn record_only_snapshots_every_interval() {
+        let mut rewind = Rewind::new(3, usize::MAX);
+        let ahoy = Ahoy::default();
+
+        for _ in 0..7 {
+            rewind.record(&ahoy).unwrap();
+        }
+
+        assert_eq!(rewind.len(), 2);
+    }
+
+    #[test]
+    fn rewind_restores_snapshots_newest_first() {
+        let mut rewind = Rewind::new(1, usize::MAX);
+        let mut ahoy = Ahoy::default();
+        for value in 0..5 {
+            ahoy.registers[0] = value;
+            rewind.record(&ahoy).unwrap();
+        }
+
+        for expected in (0..5).rev() {
+            assert_eq!(rewind.rewind().unwrap().unwrap().registers[0], expected);
+        }
+        // The oldest snapshot stays put once everything else is consumed
+        assert_eq!(rewind.rewind().unwrap().unwrap().registers[0], 0);
+    }
+
+    #[test]
+    fn record_drops_oldest_snapshots_over_budget() {
+        let mut state = Vec::new();
+        Ahoy::default().save_state(&mut state).unwrap();
+        let mut rewind = Rewind::new(1, state.len() + 64);
+        let mut ahoy = Ahoy::default();
+
+        for value in 0..100 {
+            ahoy.current_frame[value % 32] ^= u64::MAX;
+            rewind.record(&ahoy).unwrap();
+        }
+
+        assert!(rewind.used_bytes() <= state.len() + 64);
+        assert!(rewind.len() < 100);
+    }
+}
@@ -0,0 +1,320 @@
+use std::{collections::HashSet, fmt, ops::Range};
+
+use cli_log::warn;
+
+use crate::{
+    constants::{FONT, FONT_START, MAX_MEMORY},
+    instructions::AhoyInstruction,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SanitizerIssue {
+    ExecutedUnloadedAddress,
+    FontOverwrite(usize),
+    CodeOverwrite(usize),
+    UninitializedRead(usize),
+    OddJump(usize),
+    EmptyStackReturn,
+    SpritePastProgramEnd(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SanitizerReport {
+    pub pc: usize,
+    pub opcode: u16,
+    pub issue: SanitizerIssue,
+}
+
+impl fmt::Display for SanitizerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:03X} [{:04X}] ", self.pc, self.opcode)?;
+        match self.issue {
+            SanitizerIssue::ExecutedUnloadedAddress => {
+                write!(f, "executed an address never loaded by the ROM")
+            }
+            SanitizerIssue::FontOverwrite(addr) => write!(f, "wrote over the font at 0x{addr:03X}"),
+            SanitizerIssue::CodeOverwrite(addr) => {
+                write!(f, "wrote over executed code at 0x{addr:03X}")
+            }
+            SanitizerIssue::UninitializedRead(addr) => {
+                write!(f, "read uninitialized memory at 0x{addr:03X}")
+            }
+            SanitizerIssue::OddJump(addr) => write!(f, "jumped to odd address 0x{addr:03X}"),
+            SanitizerIssue::EmptyStackReturn => write!(f, "returned with an empty stack"),
+            SanitizerIssue::SpritePastProgramEnd(addr) => {
+                write!(f, "read sprite data past the ROM end at 0x{addr:03X}")
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Sanitizer {
+    program: Range<usize>,
+    initialized: Vec<bool>,
+    executed: Vec<bool>,
+    reports: Vec<SanitizerReport>,
+    seen: HashSet<SanitizerReport>,
+}
+
+impl Default for Sanitizer {
+    fn default() -> Self {
+        let mut initialized = vec![false; MAX_MEMORY];
+        initialized[FONT_START..FONT_START + FONT.len()].fill(true);
+        Self {
+            program: 0..0,
+            initialized,
+            executed: vec![false; MAX_MEMORY],
+            reports: Vec::new(),
+            seen: HashSet::new(),
+        }
+    }
+}
+
+impl Sanitizer {
+    pub fn reports(&self) -> &[SanitizerReport] {
+        &self.reports
+    }
+
+    pub(crate) fn mark_loaded(&mut self, program: Range<usize>) {
+        self.initialized[program.clone()].fill(true);
+        self.program = program;
+    }
+
+    pub(crate) fn check_instruction(
+        &mut self,
+        pc: usize,
+        opcode: u16,
+        instruction: &AhoyInstruction,
+        index: usize,
+        stack_depth: usize,
+    ) {
+        self.executed[pc] = true;
+        self.executed[(pc + 1) % MAX_MEMORY] = true;
+        if !self.program.contains(&pc) {
+            self.report(pc, opcode, SanitizerIssue::ExecutedUnloadedAddress);
+        }
+
+        match *instruction {
+            AhoyInstruction::Jump(addr) if addr % 2 == 1 => {
+                self.report(pc, opcode, SanitizerIssue::OddJump(addr))
+            }
+            AhoyInstruction::CallSubroutine(addr) if addr % 2 == 1 => {
+                self.report(pc, opcode, SanitizerIssue::OddJump(addr as usize))
+            }
+            AhoyInstruction::StopSubroutine if stack_depth == 0 => {
+                self.report(pc, opcode, SanitizerIssue::EmptyStackReturn)
+            }
+            AhoyInstruction::Display { sprite_height, .. } => {
+                let sprite =
+                    (0..sprite_height as usize).map(|offset| (index + offset) % MAX_MEMORY);
+                let font = FONT_START..FONT_START + FONT.len();
+                if let Some(addr) = sprite
+                    .clone()
+                    .find(|addr| !self.program.contains(addr) && !font.contains(addr))
+                {
+                    self.report(pc, opcode, SanitizerIssue::SpritePastProgramEnd(addr));
+                }
+                if let Some(addr) = sprite.clone().find(|addr| !self.initialized[*addr]) {
+                    self.report(pc, opcode, SanitizerIssue::UninitializedRead(addr));
+                }
+            }
+            AhoyInstruction::StoreRegisters(last_register) => {
+                for offset in 0..=last_register {
+                    self.check_write(pc, opcode, index + offset);
+                }
+            }
+            AhoyInstruction::StoreDecimal(_) => {
+                for offset in 0..3 {
+                    self.check_write(pc, opcode, index + offset);
+                }
+            }
+            AhoyInstruction::LoadRegisters(last_register) => {
+                if let Some(addr) = (0..=last_register)
+                    .map(|offset| (index + offset) % MAX_MEMORY)
+                    .find(|addr| !self.initialized[*addr])
+                {
+                    self.report(pc, opcode, SanitizerIssue::UninitializedRead(addr));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn check_write(&mut self, pc: usize, opcode: u16, addr: usize) {
+        let addr = addr % MAX_MEMORY;
+        if (FONT_START..FONT_START + FONT.len()).contains(&addr) {
+            self.report(pc, opcode, SanitizerIssue::FontOverwrite(addr));
+        } else if self.executed[addr] {
+            self.report(pc, opcode, SanitizerIssue::CodeOverwrite(addr));
+        }
+        self.initialized[addr] = true;
+    }
+
+    fn report(&mut self, pc: usize, opcode: u16, issue: SanitizerIssue) {
+        let report = SanitizerReport { pc, opcode, issue };
+        if self.seen.insert(report) {
+            warn!("SANITIZER > {}", report);
+            self.reports.push(report);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Cursor};
+
+    use crate::{
+        Ahoy,
+        sanitizer::{Sanitizer, SanitizerIssue},
+    };
+
+    fn sanitized_ahoy(program: &[u8]) -> Ahoy {
+        let mut ahoy = Ahoy::default();
+        ahoy.enable_sanitizer();
+        ahoy.load(&mut BufReader::new(Cursor::new(program.to_vec())))
+            .unwrap();
+        ahoy
+    }
+
+    fn issues(ahoy: &Ahoy) -> Vec<SanitizerIssue> {
+        ahoy.sanitizer_reports()
+            .iter()
+            .map(|report| report.issue)
+            .collect()
+    }
+
+    #[test]
+    fn clean_program_has_no_reports() {
+        // LD I,font 0 / DRW V0,V0,5 / JP 0x204
+        let mut ahoy = sanitized_ahoy(&[0xA0, 0x50, 0xD0, 0x05, 0x12, 0x04]);
+        for _ in 0..10 {
+            ahoy.process().unwrap();
+        }
+
+        assert!(ahoy.sanitizer_reports().is_empty());
+    }
+
+    #[test]
+    fn reports_running_off_the_end_of_the_program() {
+        let mut ahoy = sanitized_ahoy(&[0x60, 0x01]);
+        ahoy.process().unwrap();
+        ahoy.process().unwrap();
+
+        let report = ahoy.sanitizer_reports()[0];
+        assert_eq!(report.pc, 0x202);
+        assert_eq!(report.issue, SanitizerIssue::ExecutedUnloadedAddress);
+    }
+
+    #[test]
+    fn reports_odd_jumps_once_per_location() {
+        // JP 0x203 / pad / JP 0x201, both misaligned
+        let mut ahoy = sanitized_ahoy(&[0x12, 0x03, 0x00, 0x12, 0x01, 0x00]);
+        for _ in 0..4 {
+            ahoy.process().unwrap();
+        }
+
+        assert_eq!(issues(&ahoy)[0], SanitizerIssue::OddJump(0x203));
+        assert_eq!(
+            issues(&ahoy)
+                .iter()
+                .filter(|issue| **issue == SanitizerIssue::OddJump(0x203))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn reports_returns_with_an_empty_stack() {
+        let mut ahoy = sanitized_ahoy(&[0x00, 0xEE]);
+        ahoy.process()
+            .expect_err("Expected empty stack return to fail");
+
+        assert_eq!(issues(&ahoy), vec![SanitizerIssue::EmptyStackReturn]);
+    }
+
+    #[test]
+    fn reports_sprites_read_past_the_program() {
+        // LD I,0x204 / DRW V0,V0,4
+        let mut ahoy = sanitized_ahoy(&[0xA2, 0x04, 0xD0, 0x04, 0xFF, 0xFF]);
+        ahoy.process().unwrap();
+        ahoy.process().unwrap();
+
+        assert_eq!(
+            issues(&ahoy),
+            vec![
+                SanitizerIssue::SpritePastProgramEnd(0x206),
+                SanitizerIssue::UninitializedRead(0x206)
+            ]
+        );
+    }
+
+    #[test]
+    fn check_write_reports_font_and_code_overwrites() {
+        let mut sanitizer = Sanitizer::default();
+        sanitizer.mark_loaded(0x200..0x210);
+        sanitizer.check_instruction(0x200, 0x6000, &0x6000.into(), 0, 0);
+
+        sanitizer.check_write(0x202, 0xF055, 0x050);
+        sanitizer.check_write(0x202, 0xF055, 0x201);
+        sanitizer.check_write(0x202, 0xF055, 0x208);
+
+        let issues: Vec<_> = sanitizer
+            .reports()
+            .iter()
+            .map(|report| report.issue)
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                SanitizerIssue::FontOverwrite(0x050),
+                SanitizerIssue::CodeOverwrite(0x201)
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_register_stores_over_code_and_loads_of_uninitialized_memory() {
+        // LD I,0x200 / LD [I],V1 / LD I,0x300 / LD V0,[I]
+        let mut ahoy = sanitized_ahoy(&[0xA2, 0x00, 0xF1, 0x55, 0xA3, 0x00, 0xF0, 0x65]);
+        for _ in 0..4 {
+            ahoy.process().unwrap();
+        }
+
+        assert_eq!(
+            issues(&ahoy),
+            vec![
+                SanitizerIssue::CodeOverwrite(0x200),
+                SanitizerIssue::CodeOverwrite(0x201),
+                SanitizerIssue::UninitializedRead(0x300)
+            ]
+        );
+    }
+
+    #[test]
+    fn stored_digits_count_as_initialized() {
+        // LD I,0x300 / LD B,V0 / LD V2,[I]
+        let mut ahoy = sanitized_ahoy(&[0xA3, 0x00, 0xF0, 0x33, 0xF2, 0x65]);
+        for _ in 0..3 {
+            ahoy.process().unwrap();
+        }
+
+        assert!(ahoy.sanitizer_reports().is_empty());
+    }
+
+    #[test]
+    fn restoring_a_state_keeps_the_sanitizer() {
+        let mut ahoy = sanitized_ahoy(&[0x60, 0x01]);
+        let mut saved = Vec::new();
+        ahoy.save_state(&mut saved).unwrap();
+        ahoy.process().unwrap();
+        ahoy.process().unwrap();
+
+        ahoy.restore(Ahoy::load_state(&mut Cursor::new(&saved)).unwrap());
+
+        assert_eq!(issues(&ahoy), vec![SanitizerIssue::ExecutedUnloadedAddress]);
+        ahoy.process().unwrap();
+        ahoy.process().unwrap();
+        assert_eq!(ahoy.sanitizer_reports().len(), 1);
+    }
+}
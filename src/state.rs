@@ -0,0 +1,272 @@
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+};
+
+use anyhow::anyhow;
+
+use crate::{
+    Ahoy,
+    constants::{MAX_MEMORY, MAX_STACK_DEPTH},
+    display::{AhoyFrame, DISPLAY_HEIGHT},
+    quirks::Quirks,
+};
+
+pub const STATE_MAGIC: [u8; 4] = *b"AHOY";
+pub const STATE_VERSION: u16 = 2;
+// Stored in place of the key while FX0A has not seen a press
+const NO_AWAITED_KEY: u8 = 0xFF;
+
+impl Ahoy {
+    pub fn save_state<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        writer.write_all(&STATE_MAGIC)?;
+        writer.write_all(&STATE_VERSION.to_le_bytes())?;
+
+        writer.write_all(&self.memory)?;
+        writer.write_all(&self.registers)?;
+        writer.write_all(&(self.index as u16).to_le_bytes())?;
+        writer.write_all(&(self.counter as u16).to_le_bytes())?;
+
+        writer.write_all(&(self.stack.len() as u16).to_le_bytes())?;
+        for addr in &self.stack {
+            writer.write_all(&addr.to_le_bytes())?;
+        }
+
+        writer.write_all(&[self.delay_timer, self.sound_timer])?;
+        for row in &self.current_frame {
+            writer.write_all(&row.to_le_bytes())?;
+        }
+        writer.write_all(&self.keypad.to_le_bytes())?;
+        writer.write_all(&[self.awaited_key.unwrap_or(NO_AWAITED_KEY)])?;
+        writer.write_all(&self.rng_state.to_le_bytes())?;
+        writer.write_all(&[self.quirks.to_bits()])?;
+
+        Ok(())
+    }
+
+    // Swaps in a loaded state without losing what the sanitizer saw so far
+    pub fn restore(&mut self, mut loaded: Ahoy) {
+        loaded.sanitizer = self.sanitizer.take();
+        *self = loaded;
+    }
+
+    pub fn load_state<R: Read>(reader: &mut R) -> anyhow::Result<Self> {
+        let magic: [u8; 4] = read_array(reader)?;
+        if magic != STATE_MAGIC {
+            return Err(anyhow!("Not an Ahoy save state"));
+        }
+
+        let version = u16::from_le_bytes(read_array(reader)?);
+        if version < STATE_VERSION {
+            return Err(anyhow!(
+                "Save state format v{} is older than the supported v{}",
+                version,
+                STATE_VERSION
+            ));
+        }
+        if version > STATE_VERSION {
+            return Err(anyhow!(
+                "Save state format v{} is newer than the supported v{}",
+                version,
+                STATE_VERSION
+            ));
+        }
+
+        let memory: [u8; MAX_MEMORY] = read_array(reader)?;
+        let registers: [u8; 16] = read_array(reader)?;
+        let index = u16::from_le_bytes(read_array(reader)?) as usize;
+        let counter = u16::from_le_bytes(read_array(reader)?) as usize;
+        if index >= MAX_MEMORY || counter >= MAX_MEMORY {
+            return Err(anyhow!("Save state points outside of memory"));
+        }
+
+        let stack_len = u16::from_le_bytes(read_array(reader)?) as usize;
+        if stack_len > MAX_STACK_DEPTH {
+            return Err(anyhow!(
+                "Save state stack holds {} entries, more than the limit of {}",
+                stack_len,
+                MAX_STACK_DEPTH
+            ));
+        }
+        let mut stack = VecDeque::with_capacity(MAX_STACK_DEPTH);
+        for _ in 0..stack_len {
+            stack.push_back(u16::from_le_bytes(read_array(reader)?));
+        }
+
+        let [delay_timer, sound_timer] = read_array(reader)?;
+        let mut current_frame: AhoyFrame = [0; DISPLAY_HEIGHT];
+        for row in current_frame.iter_mut() {
+            *row = u64::from_le_bytes(read_array(reader)?);
+        }
+        let keypad = u16::from_le_bytes(read_array(reader)?);
+        let awaited_key = match read_array(reader)? {
+            [NO_AWAITED_KEY] => None,
+            [key] if key < 16 => Some(key),
+            [key] => return Err(anyhow!("Save state waits for unknown key {}", key)),
+        };
+        let rng_state = u64::from_le_bytes(read_array(reader)?);
+        let [quirk_bits] = read_array(reader)?;
+
+        if reader.read(&mut [0])? != 0 {
+            return Err(anyhow!("Save state has trailing data"));
+        }
+
+        Ok(Ahoy {
+            memory,
+            registers,
+            index,
+            counter,
+            stack,
+            delay_timer,
+            sound_timer,
+            current_frame,
+            frame_generation: 0,
+            keypad,
+            awaited_key,
+            rng_state,
+            quirks: Quirks::from_bits(quirk_bits),
+            sanitizer: None,
+        })
+    }
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> anyhow::Result<[u8; N]> {
+    let mut buffer = [0; N];
+    reader
+        .read_exact(&mut buffer)
+        .map_err(|_| anyhow!("Save state is truncated"))?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{Ahoy, constants::MAX_STACK_DEPTH, quirks::Quirks, state::STATE_VERSION};
+
+    fn busy_ahoy() -> Ahoy {
+        let mut ahoy = Ahoy {
+            index: 0x2AB,
+            counter: 0x3CE,
+            delay_timer: 0x12,
+            sound_timer: 0x34,
+            quirks: Quirks {
+                vf_reset: true,
+                clipping: true,
+                jumping: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        ahoy.memory[0x200..0x204].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        ahoy.registers[0x3] = 0x42;
+        ahoy.stack.extend([0x204, 0x6A0]);
+        ahoy.current_frame[0] = 0xF0F0F0F0F0F0F0F0;
+        ahoy.current_frame[31] = 0x1;
+        ahoy.set_key(0xA, true);
+        ahoy.awaited_key = Some(0xA);
+        ahoy.seed_rng(0xC0FFEE);
+        ahoy
+    }
+
+    #[test]
+    fn save_and_load_state_is_bit_exact() {
+        let ahoy = busy_ahoy();
+        let mut saved = Vec::new();
+        ahoy.save_state(&mut saved).unwrap();
+
+        let restored = Ahoy::load_state(&mut Cursor::new(&saved)).unwrap();
+        let mut resaved = Vec::new();
+        restored.save_state(&mut resaved).unwrap();
+
+        assert_eq!(saved, resaved);
+        assert_eq!(restored.memory, ahoy.memory);
+        assert_eq!(restored.registers, ahoy.registers);
+        assert_eq!(restored.index, 0x2AB);
+        assert_eq!(restored.counter, 0x3CE);
+        assert_eq!(restored.stack, ahoy.stack);
+        assert_eq!(restored.delay_timer, 0x12);
+        assert_eq!(restored.sound_timer, 0x34);
+        assert_eq!(restored.current_frame, ahoy.current_frame);
+        assert_eq!(restored.keypad, ahoy.keypad);
+        assert_eq!(restored.awaited_key, Some(0xA));
+        assert_eq!(restored.rng_state, ahoy.rng_state);
+        assert_eq!(restored.quirks, ahoy.quirks);
+    }
+
+    #[test]
+    fn load_state_rejects_older_format_version() {
+        let mut saved = Vec::new();
+        busy_ahoy().save_state(&mut saved).unwrap();
+        saved[4..6].copy_from_slice(&(STATE_VERSION - 1).to_le_bytes());
+
+        let error = Ahoy::load_state(&mut Cursor::new(&saved))
+            .err()
+            .expect("Expected older state version to raise error");
+        assert!(error.to_string().contains("older"));
+    }
+
+    #[test]
+    fn load_state_rejects_unknown_files() {
+        let mut saved = Vec::new();
+        busy_ahoy().save_state(&mut saved).unwrap();
+        saved[0..4].copy_from_slice(b"NOPE");
+
+        Ahoy::load_state(&mut Cursor::new(&saved))
+            .err()
+            .expect("Expected bad magic to raise error");
+    }
+
+    #[test]
+    fn load_state_rejects_truncated_files() {
+        let mut saved = Vec::new();
+        busy_ahoy().save_state(&mut saved).unwrap();
+        saved.truncate(saved.len() - 1);
+
+        Ahoy::load_state(&mut Cursor::new(&saved))
+            .err()
+            .expect("Expected truncated state to raise error");
+    }
+
+    #[test]
+    fn load_state_rejects_stacks_past_the_limit() {
+        let mut ahoy = busy_ahoy();
+        ahoy.stack = vec![0x200; MAX_STACK_DEPTH + 1].into();
+        let mut saved = Vec::new();
+        ahoy.save_state(&mut saved).unwrap();
+
+        let error = Ahoy::load_state(&mut Cursor::new(&saved))
+            .err()
+            .expect("Expected an oversized stack to raise error");
+        assert!(error.to_string().contains("stack"));
+    }
+
+    #[test]
+    fn load_state_resumes_a_half_finished_key_wait() {
+        let mut ahoy = Ahoy::default();
+        ahoy.memory[0x200..0x202].copy_from_slice(&[0xF3, 0x0A]);
+        ahoy.set_key(0x7, true);
+        ahoy.process().unwrap();
+        let mut saved = Vec::new();
+        ahoy.save_state(&mut saved).unwrap();
+
+        let mut restored = Ahoy::load_state(&mut Cursor::new(&saved)).unwrap();
+        restored.set_key(0x7, false);
+        restored.process().unwrap();
+
+        assert_eq!(restored.registers[0x3], 0x7);
+        assert_eq!(restored.counter, 0x202);
+    }
+
+    #[test]
+    fn load_state_rejects_unknown_awaited_keys() {
+        let mut ahoy = busy_ahoy();
+        ahoy.awaited_key = Some(0x10);
+        let mut saved = Vec::new();
+        ahoy.save_state(&mut saved).unwrap();
+
+        Ahoy::load_state(&mut Cursor::new(&saved))
+            .err()
+            .expect("Expected an unknown awaited key to raise error");
+    }
+}
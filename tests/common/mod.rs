@@ -0,0 +1,47 @@
+use std::{env, fs, path::PathBuf};
+
+use ahoy::{
+    display::AhoyFrame,
+    export::{read_ascii, write_ascii},
+};
+
+// Set AHOY_UPDATE_GOLDENS=1 to (re)write goldens from the current output
+const UPDATE_GOLDENS: &str = "AHOY_UPDATE_GOLDENS";
+
+pub fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/goldens")
+        .join(format!("{name}.txt"))
+}
+
+pub fn load_golden(name: &str, frame: &AhoyFrame) -> Result<AhoyFrame, String> {
+    let path = golden_path(name);
+    if env::var_os(UPDATE_GOLDENS).is_some() {
+        let mut text = Vec::new();
+        write_ascii(frame, &mut text).unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, text).unwrap();
+    }
+
+    let text = fs::read_to_string(&path).map_err(|_| {
+        format!(
+            "missing golden {}, rerun with {UPDATE_GOLDENS}=1 to create it",
+            path.display()
+        )
+    })?;
+    Ok(read_ascii(&text).unwrap())
+}
+
+#[allow(dead_code)]
+pub fn assert_frame_snapshot(name: &str, frame: &AhoyFrame) {
+    let golden = load_golden(name, frame).unwrap_or_else(|error| panic!("{error}"));
+    if &golden != frame {
+        let mut actual = Vec::new();
+        write_ascii(frame, &mut actual).unwrap();
+        panic!(
+            "Frame does not match golden {}, got:\n{}",
+            golden_path(name).display(),
+            String::from_utf8(actual).unwrap()
+        );
+    }
+}
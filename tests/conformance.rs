@@ -0,0 +1,47 @@
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use ahoy::{
+    conformance::{TEST_SUITE, compare_with_golden, run_test_rom},
+    quirks::Platform,
+};
+use common::load_golden;
+
+// Run with `cargo test -- --ignored` once the ROMs are in assets/roms/tests
+#[test]
+#[ignore = "needs the community test ROMs in assets/roms/tests"]
+fn community_test_suite_matches_goldens() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let mut failures = Vec::new();
+
+    for rom in &TEST_SUITE {
+        let path = root.join("assets/roms/tests").join(rom.file);
+        let program = fs::read(&path)
+            .unwrap_or_else(|error| panic!("Could not read {}: {error}", path.display()));
+        let stem = rom.file.trim_end_matches(".ch8");
+
+        for platform in Platform::ALL {
+            let label = format!("{} [{}]", rom.file, platform.name());
+            let frame = run_test_rom(&program, rom, platform).unwrap();
+            let golden = match load_golden(&format!("{}-{}", stem, platform.name()), &frame) {
+                Ok(golden) => golden,
+                Err(error) => {
+                    eprintln!("FAIL {label}: {error}");
+                    failures.push(label);
+                    continue;
+                }
+            };
+
+            for check in compare_with_golden(&frame, &golden, rom) {
+                let status = if check.passed { "PASS" } else { "FAIL" };
+                eprintln!("{status} {label}: {}", check.name);
+                if !check.passed {
+                    failures.push(format!("{label}: {}", check.name));
+                }
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "Conformance failures: {failures:#?}");
+}
@@ -0,0 +1,23 @@
+mod common;
+
+use std::{fs::File, io::BufReader};
+
+use ahoy::Ahoy;
+use common::assert_frame_snapshot;
+
+#[test]
+fn ibm_logo_renders_before_idling() {
+    let rom = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/roms/ibm.ch8");
+    let mut ahoy = Ahoy::default();
+    ahoy.load(&mut BufReader::new(File::open(rom).unwrap()))
+        .unwrap();
+
+    let mut steps = 0;
+    while !ahoy.is_idle() {
+        ahoy.process().unwrap();
+        steps += 1;
+        assert!(steps < 1000, "IBM logo never reached its idle loop");
+    }
+
+    assert_frame_snapshot("ibm-logo", &ahoy.current_frame);
+}
@@ -0,0 +1,49 @@
+use std::io::{BufReader, Cursor};
+
+use ahoy::{Ahoy, quirks::Quirks};
+use proptest::prelude::*;
+
+const PROGRAM_SIZE: usize = 0xE00;
+
+fn quirks() -> impl Strategy<Value = Quirks> {
+    any::<[bool; 6]>().prop_map(
+        |[
+            vf_reset,
+            memory_increment,
+            display_wait,
+            clipping,
+            shifting,
+            jumping,
+        ]| Quirks {
+            vf_reset,
+            memory_increment,
+            display_wait,
+            clipping,
+            shifting,
+            jumping,
+        },
+    )
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn arbitrary_programs_never_panic(
+        program in proptest::collection::vec(any::<u8>(), 1..=PROGRAM_SIZE),
+        quirks in quirks(),
+        seed in any::<u64>(),
+    ) {
+        let mut ahoy = Ahoy::default();
+        ahoy.quirks = quirks;
+        ahoy.seed_rng(seed);
+        ahoy.load(&mut BufReader::new(Cursor::new(program))).unwrap();
+
+        for _ in 0..10_000 {
+            // Errors are fine, panics are not
+            if ahoy.process().is_err() {
+                break;
+            }
+        }
+    }
+}